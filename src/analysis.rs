@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+
+use crate::body::{Body, BodyHandle};
+use crate::events::CollisionEvent;
+use crate::math::Vec2;
+use crate::world::World;
+
+//measurements accumulate every time sample() is called, reset() starts a new time window
+
+//a body moving further than this (on top of what its velocity explains) in one step was put there by hand
+const JUMP_TOLERANCE: f32 = 0.1;
+
+fn particles(world: &World) -> impl Iterator<Item = (BodyHandle, &Body)>{
+    world.bodies().filter(|(_, body)| body.is_dynamic())
+}
+
+//2d maxwell-boltzmann speed distribution, m v / kT * exp(-m v^2 / 2kT)
+pub fn maxwell_boltzmann_2d(speed: f32, mass: f32, temperature: f32, boltzmann: f32) -> f32{
+    let kt = boltzmann * temperature;
+    mass * speed / kt * (-mass * speed * speed / (2.0 * kt)).exp()
+}
+
+pub struct SpeedHistogram{
+    pub max_speed: f32,
+    counts: Vec<u64>,
+    samples: u64,
+}
+
+impl SpeedHistogram{
+    pub fn new(bins: usize, max_speed: f32) -> Self{
+        Self{
+            max_speed,
+            counts: vec![0; bins],
+            samples: 0,
+        }
+    }
+
+    pub fn reset(&mut self){
+        self.counts.iter_mut().for_each(|c| *c = 0);
+        self.samples = 0;
+    }
+
+    pub fn sample(&mut self, world: &World){
+        let bins = self.counts.len();
+        for (_, body) in particles(world){
+            let bin = (body.velocity.length() / self.max_speed * bins as f32) as usize;
+            if bin < bins{
+                self.counts[bin] += 1;
+            }
+            self.samples += 1;
+        }
+    }
+
+    pub fn bin_width(&self) -> f32{
+        self.max_speed / self.counts.len() as f32
+    }
+
+    //(bin center, probability density)
+    pub fn density(&self) -> Vec<(f32, f32)>{
+        let width = self.bin_width();
+        self.counts.iter().enumerate().map(|(i, count)| {
+            let density = if self.samples > 0 { *count as f32 / (self.samples as f32 * width) } else { 0.0 };
+            ((i as f32 + 0.5) * width, density)
+        }).collect()
+    }
+
+    //integrated absolute difference to the maxwell-boltzmann curve, 0 is a perfect match and 2 the worst
+    pub fn distance_to_maxwell_boltzmann(&self, mass: f32, temperature: f32, boltzmann: f32) -> f32{
+        let width = self.bin_width();
+        self.density().into_iter()
+            .map(|(speed, density)| (density - maxwell_boltzmann_2d(speed, mass, temperature, boltzmann)).abs() * width)
+            .sum()
+    }
+}
+
+//pressure on a set of walls from the momentum the contacts push into them
+pub struct PressureProbe{
+    pub walls: Vec<BodyHandle>,
+    //total length of the walls, pressure in 2d is force per length
+    pub wall_length: f32,
+    impulse: f32,
+    elapsed: f32,
+}
+
+impl PressureProbe{
+    pub fn new(walls: Vec<BodyHandle>, wall_length: f32) -> Self{
+        Self{
+            walls,
+            wall_length,
+            impulse: 0.0,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self){
+        self.impulse = 0.0;
+        self.elapsed = 0.0;
+    }
+
+    //call once after every world step
+    pub fn sample(&mut self, world: &World, dt: f32){
+        for manifold in world.contacts().iter().filter(|m| m.enabled){
+            if self.walls.contains(&manifold.a) || self.walls.contains(&manifold.b){
+                self.impulse += manifold.total_normal_impulse();
+            }
+        }
+        self.elapsed += dt;
+    }
+
+    pub fn pressure(&self) -> f32{
+        if self.elapsed <= 0.0 || self.wall_length <= 0.0{
+            return 0.0;
+        }
+        self.impulse / (self.elapsed * self.wall_length)
+    }
+}
+
+//counts particle-particle collisions to get the collision frequency and mean free path
+pub struct CollisionCounter{
+    collisions: u64,
+    speed_sum: f64,
+    speed_samples: u64,
+    particle_count: usize,
+    elapsed: f32,
+}
+
+impl CollisionCounter{
+    pub fn new() -> Self{
+        Self{
+            collisions: 0,
+            speed_sum: 0.0,
+            speed_samples: 0,
+            particle_count: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self){
+        *self = Self::new();
+    }
+
+    //call once after every world step
+    pub fn sample(&mut self, world: &World, dt: f32){
+        let is_particle = |handle: BodyHandle| world.body(handle).is_some_and(|body| body.is_dynamic());
+        for event in world.collision_events(){
+            if let CollisionEvent::Begin(contact) = event{
+                if is_particle(contact.a) && is_particle(contact.b){
+                    self.collisions += 1;
+                }
+            }
+        }
+        let mut count = 0;
+        for (_, body) in particles(world){
+            self.speed_sum += body.velocity.length() as f64;
+            self.speed_samples += 1;
+            count += 1;
+        }
+        self.particle_count = count;
+        self.elapsed += dt;
+    }
+
+    pub fn collisions(&self) -> u64{
+        self.collisions
+    }
+
+    //collisions per particle per second, every collision involves two particles
+    pub fn collision_frequency(&self) -> f32{
+        if self.elapsed <= 0.0 || self.particle_count == 0{
+            return 0.0;
+        }
+        2.0 * self.collisions as f32 / (self.particle_count as f32 * self.elapsed)
+    }
+
+    pub fn mean_speed(&self) -> f32{
+        if self.speed_samples == 0{
+            return 0.0;
+        }
+        (self.speed_sum / self.speed_samples as f64) as f32
+    }
+
+    pub fn mean_free_path(&self) -> f32{
+        let frequency = self.collision_frequency();
+        if frequency <= 0.0{
+            return f32::INFINITY;
+        }
+        self.mean_speed() / frequency
+    }
+}
+
+impl Default for CollisionCounter{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+//mean squared displacement from where the particles were at the last reset
+pub struct Displacement{
+    //displacements are summed step by step instead of subtracting the start position. a step that
+    //moves a body much further than its velocity could is a teleport (or a removed body's slot
+    //being reused) and is left out
+    last_positions: HashMap<BodyHandle, Vec2>,
+    offsets: HashMap<BodyHandle, Vec2>,
+    jumps: usize,
+    elapsed: f32,
+    //(time, msd) for every sample in the window
+    history: Vec<(f32, f32)>,
+}
+
+impl Displacement{
+    pub fn new(world: &World) -> Self{
+        let mut displacement = Self{
+            last_positions: HashMap::new(),
+            offsets: HashMap::new(),
+            jumps: 0,
+            elapsed: 0.0,
+            history: Vec::new(),
+        };
+        displacement.reset(world);
+        displacement
+    }
+
+    pub fn reset(&mut self, world: &World){
+        self.last_positions = particles(world).map(|(handle, body)| (handle, body.position)).collect();
+        self.offsets = self.last_positions.keys().map(|handle| (*handle, Vec2::zero())).collect();
+        self.jumps = 0;
+        self.elapsed = 0.0;
+        self.history.clear();
+    }
+
+    //call once after every world step
+    pub fn sample(&mut self, world: &World, dt: f32){
+        for (handle, offset) in self.offsets.iter_mut(){
+            if let Some(body) = world.body(*handle){
+                let last = self.last_positions.get_mut(handle).unwrap();
+                //minimum image, so wrapping around a periodic edge doesn't count as a jump across the box
+                let step = world.separation(*last, body.position);
+                *last = body.position;
+                //contacts and position correction move bodies a bit more than their velocity says
+                if step.length() > body.velocity.length() * dt * 2.0 + JUMP_TOLERANCE{
+                    self.jumps += 1;
+                    continue;
+                }
+                *offset += step;
+            }
+        }
+        self.elapsed += dt;
+        let msd = self.mean_squared_displacement();
+        self.history.push((self.elapsed, msd));
+    }
+
+    pub fn mean_squared_displacement(&self) -> f32{
+        if self.offsets.is_empty(){
+            return 0.0;
+        }
+        self.offsets.values().map(|offset| offset.length_squared()).sum::<f32>() / self.offsets.len() as f32
+    }
+
+    pub fn history(&self) -> &[(f32, f32)]{
+        &self.history
+    }
+
+    //steps left out because a body jumped
+    pub fn jumps(&self) -> usize{
+        self.jumps
+    }
+
+    //einstein relation in 2d, msd = 4 D t, fitted through the origin
+    pub fn diffusion_coefficient(&self) -> f32{
+        let (mut tt, mut tm) = (0.0, 0.0);
+        for (t, msd) in self.history.iter(){
+            tt += t * t;
+            tm += t * msd;
+        }
+        if tt <= 0.0{
+            return 0.0;
+        }
+        tm / tt / 4.0
+    }
+}
+
+//radial distribution function g(r) inside a box of the given area, in a periodic world
+//that is PeriodicBounds::area and max_radius should stay under half the box
+pub struct RadialDistribution{
+    pub max_radius: f32,
+    pub area: f32,
+    counts: Vec<u64>,
+    samples: u64,
+    particle_sum: u64,
+}
+
+impl RadialDistribution{
+    pub fn new(bins: usize, max_radius: f32, area: f32) -> Self{
+        Self{
+            max_radius,
+            area,
+            counts: vec![0; bins],
+            samples: 0,
+            particle_sum: 0,
+        }
+    }
+
+    pub fn reset(&mut self){
+        self.counts.iter_mut().for_each(|c| *c = 0);
+        self.samples = 0;
+        self.particle_sum = 0;
+    }
+
+    pub fn sample(&mut self, world: &World){
+        let positions: Vec<Vec2> = particles(world).map(|(_, body)| body.position).collect();
+        let bins = self.counts.len();
+        for i in 0..positions.len(){
+            for j in (i + 1)..positions.len(){
+                let r = world.separation(positions[i], positions[j]).length();
+                let bin = (r / self.max_radius * bins as f32) as usize;
+                if bin < bins{
+                    self.counts[bin] += 2;
+                }
+            }
+        }
+        self.samples += 1;
+        self.particle_sum += positions.len() as u64;
+    }
+
+    //(bin center, g), g tends to 1 for an ideal gas
+    pub fn values(&self) -> Vec<(f32, f32)>{
+        let width = self.max_radius / self.counts.len() as f32;
+        let particles = if self.samples > 0 { self.particle_sum as f32 / self.samples as f32 } else { 0.0 };
+        let density = particles / self.area;
+        self.counts.iter().enumerate().map(|(i, count)| {
+            let r0 = i as f32 * width;
+            let r1 = r0 + width;
+            let shell = std::f32::consts::PI * (r1 * r1 - r0 * r0);
+            let ideal = density * shell * particles * self.samples as f32;
+            ((r0 + r1) * 0.5, if ideal > 0.0 { *count as f32 / ideal } else { 0.0 })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::random::Rng;
+    use crate::restitution::RestitutionModel;
+    use crate::shape::Shape;
+
+    const DT: f32 = 1.0 / 120.0;
+
+    //elastic disks in a closed box of side l, (world, walls)
+    fn boxed_gas(seed: u64, l: f32) -> (World, Vec<BodyHandle>){
+        let mut rng = Rng::new(seed);
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        //every impact bounces, so the gas keeps its temperature
+        world.restitution_model = RestitutionModel::Threshold{ threshold: 0.0 };
+        let walls = vec![
+            world.add_body(Body::new_static(Shape::rect(l + 2.0, 1.0), Vec2::new(l / 2.0, -0.5))),
+            world.add_body(Body::new_static(Shape::rect(l + 2.0, 1.0), Vec2::new(l / 2.0, l + 0.5))),
+            world.add_body(Body::new_static(Shape::rect(1.0, l), Vec2::new(-0.5, l / 2.0))),
+            world.add_body(Body::new_static(Shape::rect(1.0, l), Vec2::new(l + 0.5, l / 2.0))),
+        ];
+        for i in 0..15{
+            for j in 0..15{
+                let mut body = Body::new(Shape::circle(0.2), Vec2::new(0.6 + i as f32 * 1.25, 0.6 + j as f32 * 1.25));
+                body.velocity = Vec2::new(rng.gaussian(), rng.gaussian()) * 3.0;
+                world.add_body(body);
+            }
+        }
+        for (_, body) in world.bodies_mut(){
+            body.restitution = 1.0;
+            body.friction = 0.0;
+            body.static_friction = 0.0;
+        }
+        (world, walls)
+    }
+
+    #[test]
+    fn maxwell_boltzmann_is_normalised(){
+        let (mass, temperature) = (2.0, 1.5);
+        let width = 0.001;
+        let total: f32 = (0..20000).map(|i| maxwell_boltzmann_2d((i as f32 + 0.5) * width, mass, temperature, 1.0) * width).sum();
+        assert!((total - 1.0).abs() < 1e-3, "{total}");
+    }
+
+    #[test]
+    fn free_particles_spread_ballistically(){
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        for i in 0..4{
+            let mut body = Body::new(Shape::circle(0.1), Vec2::new(i as f32 * 5.0, 0.0));
+            body.velocity = Vec2::new(0.0, 1.0 + i as f32);
+            world.add_body(body);
+        }
+        let mut displacement = Displacement::new(&world);
+        for _ in 0..120{
+            world.step(DT);
+            displacement.sample(&world, DT);
+        }
+        //msd = <v^2> t^2 with t = 1
+        let expected = (1.0 + 4.0 + 9.0 + 16.0) / 4.0;
+        assert!((displacement.mean_squared_displacement() - expected).abs() < 0.01 * expected);
+        assert_eq!(displacement.history().len(), 120);
+        assert_eq!(displacement.jumps(), 0);
+    }
+
+    #[test]
+    fn teleports_are_left_out_of_the_displacement(){
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        let mut body = Body::new(Shape::circle(0.1), Vec2::zero());
+        body.velocity = Vec2::new(1.0, 0.0);
+        let handle = world.add_body(body);
+        let mut displacement = Displacement::new(&world);
+        for step in 0..120{
+            if step == 60{
+                world.body_mut(handle).unwrap().position.y += 50.0;
+            }
+            world.step(DT);
+            displacement.sample(&world, DT);
+        }
+        assert_eq!(displacement.jumps(), 1);
+        assert!((displacement.mean_squared_displacement() - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn elastic_gas_follows_the_ideal_gas_law(){
+        let l = 20.0;
+        let (mut world, walls) = boxed_gas(5, l);
+        let mass = world.bodies().find(|(_, body)| body.is_dynamic()).unwrap().1.mass();
+        let mut histogram = SpeedHistogram::new(20, 15.0);
+        let mut probe = PressureProbe::new(walls, 4.0 * l);
+        let mut counter = CollisionCounter::new();
+        let mut temperature = 0.0;
+        for step in 0..1200{
+            world.step(DT);
+            //the grid start needs a while to forget itself
+            if step >= 200{
+                histogram.sample(&world);
+                probe.sample(&world, DT);
+                counter.sample(&world, DT);
+                temperature += world.kinetic_temperature(1.0) / 1000.0;
+            }
+        }
+        let ideal = 225.0 * temperature / (l * l);
+        let pressure = probe.pressure();
+        assert!((pressure / ideal - 1.0).abs() < 0.3, "pressure {pressure} ideal {ideal}");
+        let distance = histogram.distance_to_maxwell_boltzmann(mass, temperature, 1.0);
+        assert!(distance < 0.2, "{distance}");
+        assert!(counter.collisions() > 0);
+        //dilute hard disks, 1 / (sqrt(2) n 2d): centres closer than a diameter d on either side hit
+        let expected = 1.0 / (2.0_f32.sqrt() * 225.0 / (l * l) * 0.8);
+        let path = counter.mean_free_path();
+        assert!((path / expected - 1.0).abs() < 0.25, "path {path} expected {expected}");
+    }
+
+    #[test]
+    fn uniform_positions_have_a_flat_radial_distribution(){
+        let mut rng = Rng::new(9);
+        let mut world = World::new(Vec2::zero());
+        let mut distribution = RadialDistribution::new(10, 3.0, 400.0);
+        for _ in 0..20{
+            let handles: Vec<BodyHandle> = (0..200).map(|_| {
+                //sensors so nothing pushes the points apart
+                let mut body = Body::new(Shape::circle(0.01), Vec2::new(rng.range(0.0, 20.0), rng.range(0.0, 20.0)));
+                body.is_sensor = true;
+                world.add_body(body)
+            }).collect();
+            distribution.sample(&world);
+            for handle in handles{
+                world.remove_body(handle);
+            }
+        }
+        //the box walls cut off shells of points near the edge, so g drops a little with r
+        for (r, g) in distribution.values(){
+            assert!(g > 0.75 && g < 1.15, "g({r}) = {g}");
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::filter::CollisionFilter;
+use crate::fracture::Breakable;
+use crate::material::Material;
+use crate::math::{self, Vec2};
+use crate::shape::{Aabb, Shape};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BodyHandle(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyType{
+    Static,
+    Dynamic,
+    //moved only by its velocity (set by hand or by a path follower), forces and contacts
+    //don't affect it but it pushes dynamic bodies like an infinitely heavy one
+    Kinematic,
+}
+
+#[derive(Clone, Debug)]
+pub struct Body{
+    pub position: Vec2,
+    pub angle: f32,
+    pub velocity: Vec2,
+    pub angular_velocity: f32,
+    pub force: Vec2,
+    pub torque: f32,
+    pub shape: Shape,
+    pub body_type: BodyType,
+    pub restitution: f32,
+    //friction while sliding, static_friction is what it takes to get a resting contact to slide
+    pub friction: f32,
+    pub static_friction: f32,
+    pub color: [f32; 3],
+    pub can_sleep: bool,
+    pub filter: CollisionFilter,
+    //sensors report overlaps but never push anything
+    pub is_sensor: bool,
+    pub charge: f32,
+    //None for bodies that never break
+    pub breakable: Option<Breakable>,
+    density: f32,
+    //never turns, contacts and off-center forces only move it
+    fixed_rotation: bool,
+    mass: f32,
+    inv_mass: f32,
+    inertia: f32,
+    inv_inertia: f32,
+    pub(crate) awake: bool,
+    pub(crate) sleep_time: f32,
+}
+
+impl Body{
+    pub fn new(shape: Shape, position: Vec2) -> Self{
+        let material = Material::default();
+        let mut body = Self{
+            position,
+            angle: 0.0,
+            velocity: Vec2::zero(),
+            angular_velocity: 0.0,
+            force: Vec2::zero(),
+            torque: 0.0,
+            shape,
+            body_type: BodyType::Dynamic,
+            restitution: material.restitution,
+            friction: material.dynamic_friction,
+            static_friction: material.static_friction,
+            color: material.color,
+            can_sleep: true,
+            filter: CollisionFilter::default(),
+            is_sensor: false,
+            charge: 0.0,
+            breakable: None,
+            density: material.density,
+            fixed_rotation: false,
+            mass: 0.0,
+            inv_mass: 0.0,
+            inertia: 0.0,
+            inv_inertia: 0.0,
+            awake: true,
+            sleep_time: 0.0,
+        };
+        body.update_mass();
+        body
+    }
+
+    pub fn new_static(shape: Shape, position: Vec2) -> Self{
+        let mut body = Self::new(shape, position);
+        body.set_body_type(BodyType::Static);
+        body
+    }
+
+    pub fn new_kinematic(shape: Shape, position: Vec2) -> Self{
+        let mut body = Self::new(shape, position);
+        body.set_body_type(BodyType::Kinematic);
+        body
+    }
+
+    pub fn new_sensor(shape: Shape, position: Vec2) -> Self{
+        let mut body = Self::new_static(shape, position);
+        body.is_sensor = true;
+        body
+    }
+
+    pub fn set_body_type(&mut self, body_type: BodyType){
+        self.body_type = body_type;
+        self.update_mass();
+    }
+
+    pub fn set_density(&mut self, density: f32){
+        self.density = density;
+        self.update_mass();
+    }
+
+    //copies everything over, the body doesn't remember which material it came from
+    pub fn set_material(&mut self, material: &Material){
+        self.restitution = material.restitution;
+        self.static_friction = material.static_friction;
+        self.friction = material.dynamic_friction;
+        self.color = material.color;
+        self.set_density(material.density);
+    }
+
+    pub fn set_shape(&mut self, shape: Shape){
+        self.shape = shape;
+        self.update_mass();
+    }
+
+    //needs to be called after changing the shape through the public field
+    pub fn update_mass(&mut self){
+        if self.body_type != BodyType::Dynamic{
+            self.mass = 0.0;
+            self.inv_mass = 0.0;
+            self.inertia = 0.0;
+            self.inv_inertia = 0.0;
+            if self.body_type == BodyType::Static{
+                self.velocity = Vec2::zero();
+                self.angular_velocity = 0.0;
+            }
+            return;
+        }
+        let (mass, inertia) = self.shape.mass_data(self.density);
+        self.mass = mass;
+        self.inv_mass = if mass > 0.0 { 1.0 / mass } else { 0.0 };
+        if self.fixed_rotation{
+            self.inertia = 0.0;
+            self.inv_inertia = 0.0;
+            self.angular_velocity = 0.0;
+        } else{
+            self.inertia = inertia;
+            self.inv_inertia = if inertia > 0.0 { 1.0 / inertia } else { 0.0 };
+        }
+    }
+
+    pub fn set_fixed_rotation(&mut self, fixed_rotation: bool){
+        self.fixed_rotation = fixed_rotation;
+        self.update_mass();
+    }
+
+    pub fn has_fixed_rotation(&self) -> bool{
+        self.fixed_rotation
+    }
+
+    pub fn density(&self) -> f32{
+        self.density
+    }
+
+    pub fn mass(&self) -> f32{
+        self.mass
+    }
+
+    pub fn inv_mass(&self) -> f32{
+        self.inv_mass
+    }
+
+    pub fn inertia(&self) -> f32{
+        self.inertia
+    }
+
+    pub fn inv_inertia(&self) -> f32{
+        self.inv_inertia
+    }
+
+    pub fn is_static(&self) -> bool{
+        self.body_type == BodyType::Static
+    }
+
+    pub fn is_dynamic(&self) -> bool{
+        self.body_type == BodyType::Dynamic
+    }
+
+    pub fn is_kinematic(&self) -> bool{
+        self.body_type == BodyType::Kinematic
+    }
+
+    pub fn is_moving(&self) -> bool{
+        self.velocity.length_squared() > 0.0 || self.angular_velocity != 0.0
+    }
+
+    pub fn is_awake(&self) -> bool{
+        self.awake
+    }
+
+    pub fn sleep_time(&self) -> f32{
+        self.sleep_time
+    }
+
+    pub fn set_awake(&mut self, awake: bool){
+        if awake{
+            self.awake = true;
+            self.sleep_time = 0.0;
+        } else{
+            self.awake = false;
+            self.sleep_time = 0.0;
+            self.velocity = Vec2::zero();
+            self.angular_velocity = 0.0;
+            self.force = Vec2::zero();
+            self.torque = 0.0;
+        }
+    }
+
+    pub fn apply_force(&mut self, force: Vec2){
+        self.force += force;
+    }
+
+    pub fn apply_force_at(&mut self, force: Vec2, point: Vec2){
+        self.force += force;
+        self.torque += (point - self.position).cross(force);
+    }
+
+    pub fn apply_torque(&mut self, torque: f32){
+        self.torque += torque;
+    }
+
+    //point is in world space, wakes the body up
+    pub fn apply_impulse(&mut self, impulse: Vec2, point: Vec2){
+        if !self.is_dynamic(){
+            return;
+        }
+        self.set_awake(true);
+        self.velocity += impulse * self.inv_mass;
+        self.angular_velocity += self.inv_inertia * (point - self.position).cross(impulse);
+    }
+
+    pub fn velocity_at(&self, point: Vec2) -> Vec2{
+        self.velocity + math::cross_sv(self.angular_velocity, point - self.position)
+    }
+
+    pub fn aabb(&self) -> Aabb{
+        self.shape.aabb(self.position, self.angle)
+    }
+
+    pub fn kinetic_energy(&self) -> f32{
+        0.5 * self.mass * self.velocity.length_squared() + 0.5 * self.inertia * self.angular_velocity * self.angular_velocity
+    }
+}
+
+//mutable access to two different bodies at the same time
+pub(crate) fn pair_mut(bodies: &mut [Option<Body>], a: usize, b: usize) -> (&mut Body, &mut Body){
+    assert!(a != b);
+    if a < b{
+        let (left, right) = bodies.split_at_mut(b);
+        (left[a].as_mut().unwrap(), right[0].as_mut().unwrap())
+    } else{
+        let (left, right) = bodies.split_at_mut(a);
+        (right[0].as_mut().unwrap(), left[b].as_mut().unwrap())
+    }
+}
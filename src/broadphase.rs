@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use crate::math::Vec2;
+use crate::shape::Aabb;
+
+//anything covering more cells than this is kept out of the grid and tested against everything
+const MAX_CELLS_PER_ENTRY: i64 = 1024;
+
+//uniform spatial hash, entries are identified by the index the caller gives them
+pub struct SpatialGrid{
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    entries: Vec<(usize, Aabb)>,
+    oversized: Vec<usize>,
+}
+
+impl SpatialGrid{
+    pub fn new(cell_size: f32) -> Self{
+        assert!(cell_size > 0.0, "cell size has to be positive");
+        Self{
+            cell_size,
+            cells: HashMap::new(),
+            entries: Vec::new(),
+            oversized: Vec::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32{
+        self.cell_size
+    }
+
+    pub fn set_cell_size(&mut self, cell_size: f32){
+        assert!(cell_size > 0.0, "cell size has to be positive");
+        self.cell_size = cell_size;
+        self.clear();
+    }
+
+    pub fn clear(&mut self){
+        //keep the allocations of the cells around between frames
+        for cell in self.cells.values_mut(){
+            cell.clear();
+        }
+        self.entries.clear();
+        self.oversized.clear();
+    }
+
+    fn cell_of(&self, point: Vec2) -> (i32, i32){
+        ((point.x / self.cell_size).floor() as i32, (point.y / self.cell_size).floor() as i32)
+    }
+
+    fn cell_range(&self, aabb: &Aabb) -> ((i32, i32), (i32, i32)){
+        (self.cell_of(aabb.min), self.cell_of(aabb.max))
+    }
+
+    pub fn insert(&mut self, index: usize, aabb: Aabb){
+        let ((x0, y0), (x1, y1)) = self.cell_range(&aabb);
+        self.entries.push((index, aabb));
+        let count = (x1 as i64 - x0 as i64 + 1) * (y1 as i64 - y0 as i64 + 1);
+        if count > MAX_CELLS_PER_ENTRY{
+            self.oversized.push(self.entries.len() - 1);
+            return;
+        }
+        let entry = self.entries.len() - 1;
+        for x in x0..=x1{
+            for y in y0..=y1{
+                self.cells.entry((x, y)).or_default().push(entry);
+            }
+        }
+    }
+
+    //drops every entry with the index, e.g. when its slot is about to be reused
+    pub fn remove(&mut self, index: usize){
+        if !self.entries.iter().any(|(i, _)| *i == index){
+            return;
+        }
+        let entries = std::mem::take(&mut self.entries);
+        self.clear();
+        for (i, aabb) in entries{
+            if i != index{
+                self.insert(i, aabb);
+            }
+        }
+    }
+
+    //indices of every entry whose aabb overlaps the given one, sorted and without duplicates
+    //(an index entered more than once, like a periodic image, still comes back once)
+    pub fn query(&self, aabb: &Aabb) -> Vec<usize>{
+        let ((x0, y0), (x1, y1)) = self.cell_range(aabb);
+        let mut found: Vec<usize> = Vec::new();
+        let count = (x1 as i64 - x0 as i64 + 1) * (y1 as i64 - y0 as i64 + 1);
+        if count > MAX_CELLS_PER_ENTRY{
+            found.extend(0..self.entries.len());
+        } else{
+            for x in x0..=x1{
+                for y in y0..=y1{
+                    if let Some(cell) = self.cells.get(&(x, y)){
+                        found.extend_from_slice(cell);
+                    }
+                }
+            }
+            found.extend_from_slice(&self.oversized);
+        }
+        found.sort_unstable();
+        found.dedup();
+        let mut indices: Vec<usize> = found.into_iter()
+            .filter(|e| self.entries[*e].1.overlaps(aabb))
+            .map(|e| self.entries[e].0)
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    pub fn query_point(&self, point: Vec2) -> Vec<usize>{
+        self.query(&Aabb::new(point, point))
+    }
+
+    //every pair of entries with overlapping aabbs, as (smaller index, bigger index)
+    pub fn pairs(&self) -> Vec<(usize, usize)>{
+        let mut pairs = Vec::new();
+        for cell in self.cells.values(){
+            for i in 0..cell.len(){
+                for j in (i + 1)..cell.len(){
+                    self.push_pair(&mut pairs, cell[i], cell[j]);
+                }
+            }
+        }
+        for (n, &big) in self.oversized.iter().enumerate(){
+            for other in 0..self.entries.len(){
+                //pairs of two oversized entries only get added once
+                if other == big || self.oversized[..n].contains(&other){
+                    continue;
+                }
+                self.push_pair(&mut pairs, big, other);
+            }
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    fn push_pair(&self, pairs: &mut Vec<(usize, usize)>, e1: usize, e2: usize){
+        let (i1, aabb1) = &self.entries[e1];
+        let (i2, aabb2) = &self.entries[e2];
+        if i1 == i2 || !aabb1.overlaps(aabb2){
+            return;
+        }
+        pairs.push(if i1 < i2 { (*i1, *i2) } else { (*i2, *i1) });
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn square(x: f32, y: f32, size: f32) -> Aabb{
+        Aabb::new(Vec2::new(x, y), Vec2::new(x + size, y + size))
+    }
+
+    #[test]
+    fn query_and_pairs_find_overlaps(){
+        let mut grid = SpatialGrid::new(1.0);
+        grid.insert(0, square(0.0, 0.0, 1.5));
+        grid.insert(1, square(1.0, 1.0, 1.0));
+        grid.insert(2, square(5.0, 5.0, 1.0));
+        //big enough to be kept out of the cells
+        grid.insert(3, square(-50.0, -50.0, 100.0));
+        assert_eq!(grid.pairs(), vec![(0, 1), (0, 3), (1, 3), (2, 3)]);
+        assert_eq!(grid.query(&square(1.2, 1.2, 0.1)), vec![0, 1, 3]);
+        assert_eq!(grid.query_point(Vec2::new(5.5, 5.5)), vec![2, 3]);
+    }
+
+    #[test]
+    fn an_index_entered_twice_comes_back_once(){
+        let mut grid = SpatialGrid::new(1.0);
+        grid.insert(4, square(0.0, 0.0, 1.0));
+        grid.insert(2, square(0.5, 0.0, 1.0));
+        //the same body again, like a periodic image, and huge so it is oversized
+        grid.insert(4, square(0.2, 0.2, 1.0));
+        grid.insert(2, square(-50.0, -50.0, 100.0));
+        assert_eq!(grid.query(&square(0.4, 0.4, 0.5)), vec![2, 4]);
+        assert_eq!(grid.pairs(), vec![(2, 4)]);
+    }
+
+    #[test]
+    fn removed_entries_are_gone(){
+        let mut grid = SpatialGrid::new(1.0);
+        grid.insert(0, square(0.0, 0.0, 1.0));
+        grid.insert(1, square(0.5, 0.5, 1.0));
+        grid.remove(0);
+        grid.insert(0, square(8.0, 8.0, 1.0));
+        assert_eq!(grid.query_point(Vec2::new(0.2, 0.2)), Vec::<usize>::new());
+        assert_eq!(grid.query_point(Vec2::new(8.5, 8.5)), vec![0]);
+        assert!(grid.pairs().is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+use thiserror::Error;
+
+use crate::body::Body;
+use crate::math::Vec2;
+use crate::shape::{self, Aabb, Shape};
+
+//circles are cut into this many sides to find how much of them is under water
+const CIRCLE_SEGMENTS: usize = 24;
+
+//area of fluid bodies float in without simulating the fluid itself. the force comes from the part
+//of the body inside the zone: buoyancy at the center of that part plus drag against the flow
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum FluidZoneError{
+    #[error("A fluid zone needs at least 3 vertices, got {0}")]
+    TooFewVertices(usize),
+    #[error("The fluid zone's vertices are all on a line")]
+    Degenerate,
+    #[error("The fluid zone isn't convex at vertex {0}, split it into convex zones")]
+    NotConvex(usize),
+}
+
+pub struct FluidZone{
+    //convex and counter clockwise
+    vertices: Vec<Vec2>,
+    bounds: Aabb,
+    pub density: f32,
+    //per second, how fast the fluid takes away the body's velocity relative to the flow
+    pub linear_drag: f32,
+    pub angular_drag: f32,
+    //velocity of the fluid, a river or a current
+    pub flow: Vec2,
+    //bodies whose filter category shares a bit with this are affected
+    pub categories: u32,
+    pub color: [f32; 3],
+}
+
+impl FluidZone{
+    //convex, in either winding. the submerged part is found by clipping against the outline,
+    //which only works for convex ones
+    pub fn polygon(vertices: &[Vec2]) -> Result<Self, FluidZoneError>{
+        if vertices.len() < 3{
+            return Err(FluidZoneError::TooFewVertices(vertices.len()));
+        }
+        let mut vertices = vertices.to_vec();
+        let area = shape::signed_area(&vertices);
+        if area.abs() <= f32::EPSILON{
+            return Err(FluidZoneError::Degenerate);
+        }
+        let count = vertices.len();
+        let winding = area.signum();
+        for i in 0..count{
+            let previous = vertices[(i + count - 1) % count];
+            let next = vertices[(i + 1) % count];
+            //straight through is fine, turning against the winding is not
+            if (vertices[i] - previous).cross(next - vertices[i]) * winding < -f32::EPSILON{
+                return Err(FluidZoneError::NotConvex(i));
+            }
+        }
+        if area < 0.0{
+            vertices.reverse();
+        }
+        Ok(Self::convex(vertices))
+    }
+
+    pub fn rect(min: Vec2, max: Vec2) -> Self{
+        Self::convex(vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)])
+    }
+
+    //vertices already checked to be convex and counter clockwise
+    fn convex(vertices: Vec<Vec2>) -> Self{
+        let bounds = vertices.iter().fold(Aabb::new(vertices[0], vertices[0]), |aabb, v| aabb.union(&Aabb::new(*v, *v)));
+        Self{
+            vertices,
+            bounds,
+            density: 1.0,
+            linear_drag: 0.5,
+            angular_drag: 0.5,
+            flow: Vec2::zero(),
+            categories: u32::MAX,
+            color: [0.2, 0.4, 1.0],
+        }
+    }
+
+    pub fn with_density(mut self, density: f32) -> Self{
+        self.density = density;
+        self
+    }
+
+    pub fn with_drag(mut self, linear_drag: f32, angular_drag: f32) -> Self{
+        self.linear_drag = linear_drag;
+        self.angular_drag = angular_drag;
+        self
+    }
+
+    pub fn with_flow(mut self, flow: Vec2) -> Self{
+        self.flow = flow;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self{
+        self.color = color;
+        self
+    }
+
+    pub fn vertices(&self) -> &[Vec2]{
+        &self.vertices
+    }
+
+    fn affects(&self, body: &Body) -> bool{
+        body.is_dynamic() && !body.is_sensor && body.filter.category & self.categories != 0
+    }
+
+    //(area, centroid) of the part of the body inside the zone, None when nothing is
+    pub fn submerged(&self, body: &Body) -> Option<(f32, Vec2)>{
+        if !body.aabb().overlaps(&self.bounds){
+            return None;
+        }
+        let outline = match &body.shape{
+            Shape::Circle{ radius } => (0..CIRCLE_SEGMENTS).map(|i| {
+                let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                body.position + Vec2::new(angle.cos(), angle.sin()) * *radius
+            }).collect(),
+            Shape::Polygon{ .. } => body.shape.world_vertices(body.position, body.angle),
+        };
+        let inside = shape::clip_convex(&outline, &self.vertices);
+        if inside.len() < 3{
+            return None;
+        }
+        let area = shape::signed_area(&inside);
+        if area <= f32::EPSILON{
+            return None;
+        }
+        //the cut up circle is a bit smaller than the real one, scale back up to its area
+        let area = match &body.shape{
+            Shape::Circle{ .. } => area * body.shape.area() / shape::signed_area(&outline),
+            Shape::Polygon{ .. } => area,
+        };
+        Some((area, shape::polygon_centroid(&inside)))
+    }
+
+    pub fn apply(&self, bodies: &mut [Option<Body>], gravity: Vec2){
+        for body in bodies.iter_mut().flatten(){
+            if !self.affects(body){
+                continue;
+            }
+            let (area, center) = match self.submerged(body){
+                Some(submerged) => submerged,
+                None => continue,
+            };
+            //a current keeps pushing bodies that settled in it
+            if !body.is_awake(){
+                if self.flow.length_squared() > 0.0{
+                    body.set_awake(true);
+                } else{
+                    continue;
+                }
+            }
+            let displaced = self.density * area;
+            let relative = body.velocity_at(center) - self.flow;
+            let force = -gravity * displaced - relative * (self.linear_drag * displaced);
+            body.apply_force_at(force, center);
+            //spin is slowed in proportion to how much of the body is in the fluid
+            if body.mass() > 0.0{
+                let torque = -self.angular_drag * displaced * body.inertia() / body.mass() * body.angular_velocity;
+                body.apply_torque(torque);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::world::World;
+
+    #[test]
+    fn only_convex_outlines_make_a_zone(){
+        let square = [Vec2::zero(), Vec2::new(2.0, 0.0), Vec2::new(2.0, 2.0), Vec2::new(0.0, 2.0)];
+        assert!(FluidZone::polygon(&square).is_ok());
+        //clockwise is turned around
+        let mut clockwise = square.to_vec();
+        clockwise.reverse();
+        let zone = FluidZone::polygon(&clockwise).unwrap();
+        assert!(shape::signed_area(zone.vertices()) > 0.0);
+        //a point in the middle of an edge is still convex
+        assert!(FluidZone::polygon(&[Vec2::zero(), Vec2::new(1.0, 0.0), Vec2::new(2.0, 0.0), Vec2::new(2.0, 2.0)]).is_ok());
+
+        assert_eq!(FluidZone::polygon(&square[..2]).err(), Some(FluidZoneError::TooFewVertices(2)));
+        let line = [Vec2::zero(), Vec2::new(1.0, 1.0), Vec2::new(2.0, 2.0)];
+        assert_eq!(FluidZone::polygon(&line).err(), Some(FluidZoneError::Degenerate));
+        //a u shape, the notch at index 4 and 5 turns the wrong way
+        let u = [
+            Vec2::zero(), Vec2::new(3.0, 0.0), Vec2::new(3.0, 3.0), Vec2::new(2.0, 3.0),
+            Vec2::new(2.0, 1.0), Vec2::new(1.0, 1.0), Vec2::new(1.0, 3.0), Vec2::new(0.0, 3.0),
+        ];
+        assert_eq!(FluidZone::polygon(&u).err(), Some(FluidZoneError::NotConvex(4)));
+        let mut reversed = u.to_vec();
+        reversed.reverse();
+        assert!(matches!(FluidZone::polygon(&reversed), Err(FluidZoneError::NotConvex(_))));
+    }
+
+    #[test]
+    fn submerged_part_is_clipped_by_the_zone(){
+        let zone = FluidZone::rect(Vec2::new(-5.0, -5.0), Vec2::new(5.0, 0.0));
+        let half = Body::new(Shape::rect(2.0, 2.0), Vec2::zero());
+        let (area, center) = zone.submerged(&half).unwrap();
+        assert!((area - 2.0).abs() < 1e-4);
+        assert!((center - Vec2::new(0.0, -0.5)).length() < 1e-4);
+        let ball = Body::new(Shape::circle(1.0), Vec2::new(0.0, -3.0));
+        let (area, _) = zone.submerged(&ball).unwrap();
+        assert!((area - std::f32::consts::PI).abs() < 1e-3);
+        assert!(zone.submerged(&Body::new(Shape::circle(1.0), Vec2::new(0.0, 3.0))).is_none());
+    }
+
+    #[test]
+    fn bodies_float_at_their_density(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        world.add_fluid_zone(FluidZone::rect(Vec2::new(-10.0, -10.0), Vec2::new(10.0, 0.0)).with_drag(2.0, 2.0));
+        let mut float = Body::new(Shape::rect(1.0, 1.0), Vec2::new(-2.0, 1.0));
+        float.set_density(0.25);
+        let float = world.add_body(float);
+        let mut sink = Body::new(Shape::rect(1.0, 1.0), Vec2::new(2.0, 1.0));
+        sink.set_density(2.0);
+        let sink = world.add_body(sink);
+        for _ in 0..600{
+            world.step(1.0 / 60.0);
+        }
+        //a quarter of it under water
+        let y = world.body(float).unwrap().position.y;
+        assert!((y - 0.25).abs() < 0.05, "{y}");
+        assert!(world.body(sink).unwrap().position.y < -5.0);
+    }
+
+    #[test]
+    fn currents_carry_bodies_along(){
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        let zone = FluidZone::rect(Vec2::new(-10.0, -10.0), Vec2::new(10.0, 10.0)).with_flow(Vec2::new(1.0, 0.0)).with_drag(2.0, 0.5);
+        world.add_fluid_zone(zone);
+        let handle = world.add_body(Body::new(Shape::circle(0.5), Vec2::zero()));
+        for _ in 0..300{
+            world.step(1.0 / 60.0);
+        }
+        let velocity = world.body(handle).unwrap().velocity;
+        assert!((velocity - Vec2::new(1.0, 0.0)).length() < 0.05, "{velocity:?}");
+    }
+}
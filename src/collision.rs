@@ -0,0 +1,252 @@
+use crate::body::{Body, BodyHandle};
+use crate::material::CombineRules;
+use crate::math::Vec2;
+use crate::shape::Shape;
+
+//polygons are allowed to sink this deep before we prefer the other reference face
+const REFERENCE_FACE_TOLERANCE: f32 = 0.0005;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ContactPoint{
+    pub point: Vec2,
+    pub penetration: f32,
+    pub normal_impulse: f32,
+    pub tangent_impulse: f32,
+    pub(crate) ra: Vec2,
+    pub(crate) rb: Vec2,
+    pub(crate) normal_mass: f32,
+    pub(crate) tangent_mass: f32,
+    pub(crate) velocity_bias: f32,
+}
+
+impl ContactPoint{
+    pub fn new(point: Vec2, penetration: f32) -> Self{
+        Self{
+            point,
+            penetration,
+            normal_impulse: 0.0,
+            tangent_impulse: 0.0,
+            ra: Vec2::zero(),
+            rb: Vec2::zero(),
+            normal_mass: 0.0,
+            tangent_mass: 0.0,
+            velocity_bias: 0.0,
+        }
+    }
+}
+
+//normal always points from body a to body b
+#[derive(Clone, Debug)]
+pub struct Manifold{
+    pub a: BodyHandle,
+    pub b: BodyHandle,
+    pub normal: Vec2,
+    pub points: Vec<ContactPoint>,
+    pub restitution: f32,
+    pub static_friction: f32,
+    pub friction: f32,
+    //turned off by a contact listener, the solver skips it
+    pub enabled: bool,
+    //how far b was moved to sit next to a, only non-zero when the pair touches across a periodic edge.
+    //contact points are where a sees them, b's own frame is at point - offset
+    pub offset: Vec2,
+}
+
+impl Manifold{
+    pub fn max_penetration(&self) -> f32{
+        self.points.iter().map(|p| p.penetration).fold(0.0, f32::max)
+    }
+
+    pub fn total_normal_impulse(&self) -> f32{
+        self.points.iter().map(|p| p.normal_impulse).sum()
+    }
+}
+
+pub fn collide(handle_a: BodyHandle, a: &Body, handle_b: BodyHandle, b: &Body, offset: Vec2, rules: &CombineRules) -> Option<Manifold>{
+    let (normal, points) = collide_shapes(&a.shape, a.position, a.angle, &b.shape, b.position + offset, b.angle)?;
+    Some(Manifold{
+        a: handle_a,
+        b: handle_b,
+        normal,
+        points,
+        restitution: rules.restitution.combine(a.restitution, b.restitution),
+        static_friction: rules.static_friction.combine(a.static_friction, b.static_friction),
+        friction: rules.dynamic_friction.combine(a.friction, b.friction),
+        enabled: true,
+        offset,
+    })
+}
+
+pub fn collide_shapes(
+    shape_a: &Shape, pos_a: Vec2, angle_a: f32,
+    shape_b: &Shape, pos_b: Vec2, angle_b: f32,
+) -> Option<(Vec2, Vec<ContactPoint>)>{
+    match (shape_a, shape_b){
+        (Shape::Circle{ radius: ra }, Shape::Circle{ radius: rb }) => collide_circles(*ra, pos_a, *rb, pos_b),
+        (Shape::Polygon{ .. }, Shape::Circle{ radius }) => collide_polygon_circle(shape_a, pos_a, angle_a, *radius, pos_b),
+        (Shape::Circle{ radius }, Shape::Polygon{ .. }) => {
+            collide_polygon_circle(shape_b, pos_b, angle_b, *radius, pos_a).map(|(normal, points)| (-normal, points))
+        },
+        (Shape::Polygon{ .. }, Shape::Polygon{ .. }) => collide_polygons(shape_a, pos_a, angle_a, shape_b, pos_b, angle_b),
+    }
+}
+
+fn collide_circles(ra: f32, pa: Vec2, rb: f32, pb: Vec2) -> Option<(Vec2, Vec<ContactPoint>)>{
+    let d = pb - pa;
+    let radius = ra + rb;
+    let dist_sq = d.length_squared();
+    if dist_sq >= radius * radius{
+        return None;
+    }
+    let dist = dist_sq.sqrt();
+    let normal = if dist > f32::EPSILON { d / dist } else { Vec2::new(1.0, 0.0) };
+    let penetration = radius - dist;
+    //halfway between the two surfaces
+    let point = pa + normal * (ra - penetration * 0.5);
+    Some((normal, vec![ContactPoint::new(point, penetration)]))
+}
+
+//normal points from the polygon to the circle
+fn collide_polygon_circle(polygon: &Shape, pa: Vec2, angle_a: f32, radius: f32, pb: Vec2) -> Option<(Vec2, Vec<ContactPoint>)>{
+    let (vertices, normals) = match polygon{
+        Shape::Polygon{ vertices, normals } => (vertices, normals),
+        Shape::Circle{ .. } => return None,
+    };
+    let center = (pb - pa).rotate(-angle_a);
+
+    let mut separation = f32::MIN;
+    let mut face = 0;
+    for i in 0..vertices.len(){
+        let s = normals[i].dot(center - vertices[i]);
+        if s > radius{
+            return None;
+        }
+        if s > separation{
+            separation = s;
+            face = i;
+        }
+    }
+
+    let v1 = vertices[face];
+    let v2 = vertices[(face + 1) % vertices.len()];
+
+    //center is inside the polygon
+    if separation < f32::EPSILON{
+        let normal = normals[face];
+        let point = center - normal * ((radius - separation) * 0.5 + separation);
+        return Some((normal.rotate(angle_a), vec![ContactPoint::new(pa + point.rotate(angle_a), radius - separation)]));
+    }
+
+    let u1 = (center - v1).dot(v2 - v1);
+    let u2 = (center - v2).dot(v1 - v2);
+    let (local_normal, closest) = if u1 <= 0.0{
+        ((center - v1).normalize(), v1)
+    } else if u2 <= 0.0{
+        ((center - v2).normalize(), v2)
+    } else{
+        (normals[face], center - normals[face] * separation)
+    };
+    let dist = (center - closest).length();
+    if dist > radius{
+        return None;
+    }
+    let penetration = radius - dist;
+    let point = closest + local_normal * (penetration * -0.5);
+    Some((local_normal.rotate(angle_a), vec![ContactPoint::new(pa + point.rotate(angle_a), penetration)]))
+}
+
+fn find_max_separation(verts1: &[Vec2], normals1: &[Vec2], verts2: &[Vec2]) -> (usize, f32){
+    let mut best_index = 0;
+    let mut best_separation = f32::MIN;
+    for i in 0..verts1.len(){
+        let n = normals1[i];
+        let v = verts1[i];
+        let s = verts2.iter().map(|p| n.dot(*p - v)).fold(f32::MAX, f32::min);
+        if s > best_separation{
+            best_separation = s;
+            best_index = i;
+        }
+    }
+    (best_index, best_separation)
+}
+
+//keeps the part of the segment where normal.dot(p) <= offset
+fn clip_segment(segment: &[Vec2], normal: Vec2, offset: f32) -> Vec<Vec2>{
+    let mut out = Vec::with_capacity(2);
+    let d0 = normal.dot(segment[0]) - offset;
+    let d1 = normal.dot(segment[1]) - offset;
+    if d0 <= 0.0{
+        out.push(segment[0]);
+    }
+    if d1 <= 0.0{
+        out.push(segment[1]);
+    }
+    if d0 * d1 < 0.0{
+        let t = d0 / (d0 - d1);
+        out.push(segment[0] + (segment[1] - segment[0]) * t);
+    }
+    out
+}
+
+fn collide_polygons(
+    shape_a: &Shape, pos_a: Vec2, angle_a: f32,
+    shape_b: &Shape, pos_b: Vec2, angle_b: f32,
+) -> Option<(Vec2, Vec<ContactPoint>)>{
+    let verts_a = shape_a.world_vertices(pos_a, angle_a);
+    let normals_a = shape_a.world_normals(angle_a);
+    let verts_b = shape_b.world_vertices(pos_b, angle_b);
+    let normals_b = shape_b.world_normals(angle_b);
+
+    let (edge_a, separation_a) = find_max_separation(&verts_a, &normals_a, &verts_b);
+    if separation_a > 0.0{
+        return None;
+    }
+    let (edge_b, separation_b) = find_max_separation(&verts_b, &normals_b, &verts_a);
+    if separation_b > 0.0{
+        return None;
+    }
+
+    let (ref_verts, ref_normals, inc_verts, inc_normals, edge, flip) = if separation_b > separation_a + REFERENCE_FACE_TOLERANCE{
+        (&verts_b, &normals_b, &verts_a, &normals_a, edge_b, true)
+    } else{
+        (&verts_a, &normals_a, &verts_b, &normals_b, edge_a, false)
+    };
+
+    let normal = ref_normals[edge];
+    let mut incident = 0;
+    let mut min_dot = f32::MAX;
+    for (i, n) in inc_normals.iter().enumerate(){
+        let d = normal.dot(*n);
+        if d < min_dot{
+            min_dot = d;
+            incident = i;
+        }
+    }
+    let incident_edge = [inc_verts[incident], inc_verts[(incident + 1) % inc_verts.len()]];
+
+    let v1 = ref_verts[edge];
+    let v2 = ref_verts[(edge + 1) % ref_verts.len()];
+    let tangent = (v2 - v1).normalize();
+
+    let clipped = clip_segment(&incident_edge, -tangent, -tangent.dot(v1));
+    if clipped.len() < 2{
+        return None;
+    }
+    let clipped = clip_segment(&clipped, tangent, tangent.dot(v2));
+    if clipped.len() < 2{
+        return None;
+    }
+
+    let points: Vec<ContactPoint> = clipped.iter().filter_map(|p| {
+        let separation = normal.dot(*p - v1);
+        if separation <= 0.0{
+            Some(ContactPoint::new(*p - normal * (separation * 0.5), -separation))
+        } else{
+            None
+        }
+    }).collect();
+    if points.is_empty(){
+        return None;
+    }
+    Some((if flip { -normal } else { normal }, points))
+}
@@ -0,0 +1,342 @@
+use crate::body::{Body, BodyHandle};
+use crate::material::Material;
+use crate::math::Vec2;
+use crate::shape::{self, Shape};
+use crate::world::World;
+
+//static walls that keep everything inside an area. every edge of the outline is its own
+//static body (a slab on the outside of the edge), so each wall can get its own restitution and friction
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContainerShape{
+    //box filling the window with the bottom left corner at the origin, follows the framebuffer size
+    Window{ width: i32, height: i32, pixels_per_unit: f32 },
+    //approximated by a regular polygon whose edges touch the circle
+    Circle{ center: Vec2, radius: f32, segments: usize },
+    //any simple polygon, doesn't have to be convex
+    Polygon{ vertices: Vec<Vec2> },
+}
+
+impl ContainerShape{
+    //inside of the container, counter clockwise
+    pub fn outline(&self) -> Vec<Vec2>{
+        match self{
+            ContainerShape::Window{ width, height, pixels_per_unit } => {
+                let size = Vec2::new(*width as f32, *height as f32) / *pixels_per_unit;
+                vec![Vec2::zero(), Vec2::new(size.x, 0.0), size, Vec2::new(0.0, size.y)]
+            },
+            ContainerShape::Circle{ center, radius, segments } => {
+                let segments = (*segments).max(3);
+                let step = std::f32::consts::TAU / segments as f32;
+                //pushed out so the middle of every edge is at the radius
+                let corner_radius = radius / (step * 0.5).cos();
+                (0..segments).map(|i| {
+                    let angle = (i as f32 + 0.5) * step;
+                    *center + Vec2::new(angle.cos(), angle.sin()) * corner_radius
+                }).collect()
+            },
+            ContainerShape::Polygon{ vertices } => {
+                let mut vertices = vertices.clone();
+                if shape::signed_area(&vertices) < 0.0{
+                    vertices.reverse();
+                }
+                vertices
+            },
+        }
+    }
+}
+
+pub struct Container{
+    pub shape: ContainerShape,
+    //how thick the walls are, thin walls let fast bodies tunnel through
+    pub thickness: f32,
+    walls: Vec<BodyHandle>,
+}
+
+impl Container{
+    pub fn new(world: &mut World, shape: ContainerShape) -> Self{
+        let mut container = Self{
+            shape,
+            thickness: 1.0,
+            walls: Vec::new(),
+        };
+        for (shape, position) in container.wall_shapes(){
+            container.walls.push(world.add_body(Body::new_static(shape, position)));
+        }
+        container
+    }
+
+    //size in pixels, the same numbers the FramebufferSize event gives
+    pub fn window(world: &mut World, width: i32, height: i32, pixels_per_unit: f32) -> Self{
+        Self::new(world, ContainerShape::Window{ width, height, pixels_per_unit })
+    }
+
+    pub fn circle(world: &mut World, center: Vec2, radius: f32, segments: usize) -> Self{
+        Self::new(world, ContainerShape::Circle{ center, radius, segments })
+    }
+
+    pub fn polygon(world: &mut World, vertices: &[Vec2]) -> Self{
+        assert!(vertices.len() >= 3, "a container needs at least 3 vertices");
+        Self::new(world, ContainerShape::Polygon{ vertices: vertices.to_vec() })
+    }
+
+    //one per edge of the outline, in outline order (for the window: bottom, right, top, left)
+    pub fn walls(&self) -> &[BodyHandle]{
+        &self.walls
+    }
+
+    pub fn set_wall_material(&self, world: &mut World, wall: usize, restitution: f32, static_friction: f32, dynamic_friction: f32){
+        if let Some(body) = self.walls.get(wall).and_then(|handle| world.body_mut(*handle)){
+            body.restitution = restitution;
+            body.static_friction = static_friction;
+            body.friction = dynamic_friction;
+        }
+    }
+
+    pub fn set_material(&self, world: &mut World, restitution: f32, static_friction: f32, dynamic_friction: f32){
+        for wall in 0..self.walls.len(){
+            self.set_wall_material(world, wall, restitution, static_friction, dynamic_friction);
+        }
+    }
+
+    pub fn apply_material(&self, world: &mut World, material: &Material){
+        for handle in self.walls.iter(){
+            if let Some(body) = world.body_mut(*handle){
+                body.set_material(material);
+            }
+        }
+    }
+
+    fn wall_shapes(&self) -> Vec<(Shape, Vec2)>{
+        let outline = self.shape.outline();
+        let mut walls = Vec::with_capacity(outline.len());
+        for i in 0..outline.len(){
+            let from = outline[i];
+            let to = outline[(i + 1) % outline.len()];
+            //for a counter clockwise outline the right hand side of an edge is outside
+            let outward = (to - from).normalize().perp() * -self.thickness;
+            let slab = [from, to, to + outward, from + outward];
+            let position = shape::polygon_centroid(&slab);
+            walls.push((Shape::polygon(&slab), position));
+        }
+        walls
+    }
+
+    //call from the FramebufferSize event, only the window container changes size.
+    //the walls are moved in place so their handles and materials stay the same
+    pub fn resize(&mut self, world: &mut World, new_width: i32, new_height: i32){
+        //a minimized window reports a size of 0, keep the old walls until it comes back
+        if new_width <= 0 || new_height <= 0{
+            return;
+        }
+        let old_outline = self.shape.outline();
+        let inside: Vec<BodyHandle> = world.bodies()
+            .filter(|(_, body)| body.is_dynamic() && !body.is_sensor && self.contains(body.position))
+            .map(|(handle, _)| handle)
+            .collect();
+        let old_size = match &mut self.shape{
+            ContainerShape::Window{ width, height, .. } => {
+                let old_size = Vec2::new(*width as f32, *height as f32);
+                *width = new_width;
+                *height = new_height;
+                old_size
+            },
+            _ => return,
+        };
+        //anything asleep against a wall that moves away has to notice, walls that only got longer
+        //or shorter along their own edge leave the bodies on them alone
+        let new_outline = self.shape.outline();
+        let mut woken = Vec::new();
+        for (i, wall) in self.walls.iter().enumerate(){
+            let (from, to) = (old_outline[i], old_outline[(i + 1) % old_outline.len()]);
+            let normal = (to - from).normalize().perp();
+            let moved = [new_outline[i], new_outline[(i + 1) % new_outline.len()]].iter().any(|p| (*p - from).dot(normal).abs() > 1e-4);
+            if moved{
+                woken.extend(world.bodies().filter(|(handle, _)| world.is_touching(*handle, *wall)).map(|(handle, _)| handle));
+            }
+        }
+        for (handle, (shape, position)) in self.walls.iter().zip(self.wall_shapes()){
+            if let Some(body) = world.body_mut(*handle){
+                body.set_shape(shape);
+                body.position = position;
+                body.angle = 0.0;
+            }
+        }
+        let scale = Vec2::new(new_width as f32 / old_size.x, new_height as f32 / old_size.y);
+        self.pull_inside(world, &inside, scale);
+        for handle in woken{
+            if let Some(body) = world.body_mut(handle){
+                body.set_awake(true);
+            }
+        }
+    }
+
+    //a shrinking window would leave bodies stuck outside the walls. only bodies that were inside
+    //and no longer fit are moved, their positions are scaled with the window so they keep their
+    //spacing, just clamping them would pile them up on the walls
+    fn pull_inside(&self, world: &mut World, inside: &[BodyHandle], scale: Vec2){
+        let outline = self.shape.outline();
+        let size = outline[2];
+        for handle in inside{
+            let body = match world.body_mut(*handle){
+                Some(body) => body,
+                None => continue,
+            };
+            let radius = body.shape.bounding_radius();
+            let fits = |value: f32, max: f32| value >= radius && value <= max - radius;
+            if fits(body.position.x, size.x) && fits(body.position.y, size.y){
+                continue;
+            }
+            if scale.x < 1.0{
+                body.position.x *= scale.x;
+            }
+            if scale.y < 1.0{
+                body.position.y *= scale.y;
+            }
+            let clamp = |value: f32, max: f32| if max < 2.0 * radius { max * 0.5 } else { value.clamp(radius, max - radius) };
+            body.position = Vec2::new(clamp(body.position.x, size.x), clamp(body.position.y, size.y));
+            body.set_awake(true);
+        }
+    }
+
+    //crossing test against the outline
+    pub fn contains(&self, point: Vec2) -> bool{
+        let outline = self.shape.outline();
+        let mut inside = false;
+        for i in 0..outline.len(){
+            let a = outline[i];
+            let b = outline[(i + 1) % outline.len()];
+            if (a.y > point.y) != (b.y > point.y){
+                let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if point.x < x{
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    pub fn remove(self, world: &mut World){
+        for handle in self.walls{
+            world.remove_body(handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn run(world: &mut World, steps: usize){
+        for _ in 0..steps{
+            world.step(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn outlines_are_counter_clockwise(){
+        let window = ContainerShape::Window{ width: 800, height: 600, pixels_per_unit: 50.0 };
+        assert_eq!(window.outline(), vec![Vec2::zero(), Vec2::new(16.0, 0.0), Vec2::new(16.0, 12.0), Vec2::new(0.0, 12.0)]);
+        let circle = ContainerShape::Circle{ center: Vec2::new(1.0, 2.0), radius: 5.0, segments: 12 }.outline();
+        assert_eq!(circle.len(), 12);
+        for i in 0..circle.len(){
+            let middle = (circle[i] + circle[(i + 1) % circle.len()]) * 0.5;
+            assert!(((middle - Vec2::new(1.0, 2.0)).length() - 5.0).abs() < 1e-4);
+        }
+        let clockwise = vec![Vec2::zero(), Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0), Vec2::new(1.0, 0.0)];
+        assert!(shape::signed_area(&ContainerShape::Polygon{ vertices: clockwise }.outline()) > 0.0);
+    }
+
+    #[test]
+    fn contains_works_for_concave_outlines(){
+        let mut world = World::new(Vec2::zero());
+        //an L with the top right corner cut out
+        let container = Container::polygon(&mut world, &[
+            Vec2::zero(), Vec2::new(4.0, 0.0), Vec2::new(4.0, 2.0), Vec2::new(2.0, 2.0), Vec2::new(2.0, 4.0), Vec2::new(0.0, 4.0),
+        ]);
+        assert_eq!(container.walls().len(), 6);
+        assert!(container.contains(Vec2::new(1.0, 3.0)));
+        assert!(container.contains(Vec2::new(3.0, 1.0)));
+        assert!(!container.contains(Vec2::new(3.0, 3.0)));
+        assert!(!container.contains(Vec2::new(-1.0, 1.0)));
+    }
+
+    #[test]
+    fn bodies_stay_inside_a_circle(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        let container = Container::circle(&mut world, Vec2::zero(), 5.0, 24);
+        let balls: Vec<BodyHandle> = (0..10).map(|i| {
+            let mut body = Body::new(Shape::circle(0.3), Vec2::new(-3.0 + i as f32 * 0.7, 2.0));
+            body.velocity = Vec2::new(if i % 2 == 0 { 8.0 } else { -8.0 }, 3.0);
+            world.add_body(body)
+        }).collect();
+        run(&mut world, 300);
+        for ball in balls{
+            let position = world.body(ball).unwrap().position;
+            assert!(container.contains(position));
+            assert!(position.length() < 5.0);
+        }
+    }
+
+    #[test]
+    fn resize_moves_the_walls_and_pulls_bodies_in(){
+        let mut world = World::new(Vec2::zero());
+        let mut container = Container::window(&mut world, 800, 600, 50.0);
+        let walls = container.walls().to_vec();
+        container.set_wall_material(&mut world, 1, 0.9, 0.1, 0.1);
+        let body = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(15.0, 6.0)));
+        container.resize(&mut world, 400, 600);
+        assert_eq!(container.walls(), &walls[..]);
+        assert_eq!(world.body(walls[1]).unwrap().restitution, 0.9);
+        //the right wall is now at x = 8, the body is scaled along with the window
+        assert!(world.body(walls[1]).unwrap().aabb().min.x >= 8.0 - 1e-4);
+        let position = world.body(body).unwrap().position;
+        assert!((position.x - 7.5).abs() < 1e-4 && position.y == 6.0);
+        //a minimized window changes nothing
+        container.resize(&mut world, 0, 0);
+        assert_eq!(container.shape, ContainerShape::Window{ width: 400, height: 600, pixels_per_unit: 50.0 });
+        container.remove(&mut world);
+        assert_eq!(world.body_count(), 1);
+    }
+
+    #[test]
+    fn frictionless_walls_let_resting_bodies_slide(){
+        //slightly tilted gravity, not enough to overcome the default static friction
+        for (friction, slides) in [(None, false), (Some(0.0), true)]{
+            let mut world = World::new(Vec2::new(0.5, -9.81));
+            let container = Container::window(&mut world, 800, 600, 50.0);
+            if let Some(friction) = friction{
+                container.set_material(&mut world, 0.0, friction, friction);
+            }
+            let block = world.add_body(Body::new(Shape::rect(1.0, 1.0), Vec2::new(4.0, 0.5)));
+            run(&mut world, 60);
+            let body = world.body(block).unwrap();
+            assert_eq!(body.position.x > 4.1, slides, "{:?}", body.position);
+        }
+    }
+
+    #[test]
+    fn resize_leaves_bodies_that_still_fit_alone(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        let mut container = Container::window(&mut world, 800, 600, 50.0);
+        let resting = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(4.0, 0.5)));
+        let cornered = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(15.5, 0.5)));
+        run(&mut world, 300);
+        assert!(!world.body(resting).unwrap().is_awake() && !world.body(cornered).unwrap().is_awake());
+        let outside = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(30.0, 6.0)));
+        let resting_at = world.body(resting).unwrap().position;
+
+        //the right wall moves away from the cornered body, the floor only gets longer
+        container.resize(&mut world, 1200, 600);
+        assert!(world.body(cornered).unwrap().is_awake());
+        assert!(!world.body(resting).unwrap().is_awake());
+        let cornered_at = world.body(cornered).unwrap().position;
+
+        container.resize(&mut world, 400, 600);
+        assert_eq!(world.body(resting).unwrap().position, resting_at);
+        assert!(!world.body(resting).unwrap().is_awake());
+        assert_eq!(world.body(outside).unwrap().position, Vec2::new(30.0, 6.0));
+        let position = world.body(cornered).unwrap().position;
+        assert!((position.x - cornered_at.x / 3.0).abs() < 1e-4 && position.y == cornered_at.y);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::body::Body;
+use crate::buffer::Buffer;
+use crate::math::{Mat4, Vec2};
+use crate::shader::{Shader, ShaderError, ShaderProgram};
+use crate::set_attribute;
+use crate::rope::Rope;
+use crate::shape::Shape;
+use crate::sph::Fluid;
+use crate::soft_body::SoftBody;
+use crate::vertex_array::VertexArray;
+use crate::world::World;
+
+//draws the simulation as flat colored shapes in world units. everything is collected while
+//drawing and sent to the gpu in one go per kind (triangles, lines, circles) by flush
+
+const SHAPE_VERTEX_SHADER: &str = r#"
+    #version 330 core
+    layout(location = 0) in vec2 a_pos;
+    layout(location = 1) in vec4 a_color;
+
+    out vec4 f_color;
+
+    uniform mat4 u_world_mat;
+
+    void main() {
+        f_color = a_color;
+        gl_Position = u_world_mat * vec4(a_pos.x, a_pos.y, 0.0, 1.0);
+    }
+"#;
+
+const SHAPE_FRAGMENT_SHADER: &str = r#"
+    #version 330 core
+    out vec4 fragColor;
+
+    in vec4 f_color;
+
+    void main() {
+        fragColor = f_color;
+    }
+"#;
+
+//one quad per circle, corners from a shared buffer and center, radius and color per instance
+const CIRCLE_VERTEX_SHADER: &str = r#"
+    #version 330 core
+    layout(location = 0) in vec2 a_corner;
+    layout(location = 1) in vec2 a_center;
+    layout(location = 2) in float a_radius;
+    layout(location = 3) in vec4 a_color;
+
+    out vec2 f_local;
+    out vec4 f_color;
+
+    uniform mat4 u_world_mat;
+
+    void main() {
+        f_local = a_corner;
+        f_color = a_color;
+        vec2 pos = a_center + a_corner * a_radius;
+        gl_Position = u_world_mat * vec4(pos.x, pos.y, 0.0, 1.0);
+    }
+"#;
+
+const CIRCLE_FRAGMENT_SHADER: &str = r#"
+    #version 330 core
+    out vec4 fragColor;
+
+    in vec2 f_local;
+    in vec4 f_color;
+
+    void main() {
+        if(dot(f_local, f_local) > 1.0){
+            discard;
+        }
+        fragColor = f_color;
+    }
+"#;
+
+const QUAD_CORNERS: [[f32; 2]; 4] = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]];
+
+type Pos = [f32; 2];
+pub type Rgba = [f32; 4];
+
+#[repr(C)]
+struct Vertex(Pos, Rgba);
+
+#[repr(C)]
+struct CircleInstance(Pos, f32, Rgba);
+
+pub fn rgba(color: [f32; 3], alpha: f32) -> Rgba{
+    [color[0], color[1], color[2], alpha]
+}
+
+//sleeping bodies are drawn faded so islands falling asleep and waking up can be seen
+const SLEEPING_ALPHA: f32 = 0.35;
+//copies of bodies hanging over a periodic edge
+const GHOST_ALPHA: f32 = 0.4;
+//overlapping fluid particles blend into a darker body of water
+const FLUID_ALPHA: f32 = 0.6;
+const OUTLINE: Rgba = [0.0, 0.0, 0.0, 0.6];
+const GRAPH_BACKGROUND: Rgba = [1.0, 1.0, 1.0, 0.15];
+
+pub struct DebugDraw{
+    world_mat: Mat4,
+    //size of the window in world units
+    view: Vec2,
+    shape_program: ShaderProgram,
+    circle_program: ShaderProgram,
+    shape_vao: VertexArray,
+    shape_vbo: Buffer,
+    circle_vao: VertexArray,
+    //the vao keeps these bound, they only have to live as long as it does
+    _corner_vbo: Buffer,
+    instance_vbo: Buffer,
+    triangles: Vec<Vertex>,
+    lines: Vec<Vertex>,
+    circles: Vec<CircleInstance>,
+}
+
+impl DebugDraw{
+    pub fn new() -> Result<Self, ShaderError>{
+        unsafe{
+            let shape_program = ShaderProgram::new(&[
+                Shader::new(SHAPE_VERTEX_SHADER, gl::VERTEX_SHADER)?,
+                Shader::new(SHAPE_FRAGMENT_SHADER, gl::FRAGMENT_SHADER)?,
+            ])?;
+            let circle_program = ShaderProgram::new(&[
+                Shader::new(CIRCLE_VERTEX_SHADER, gl::VERTEX_SHADER)?,
+                Shader::new(CIRCLE_FRAGMENT_SHADER, gl::FRAGMENT_SHADER)?,
+            ])?;
+
+            let shape_vao = VertexArray::new();
+            let shape_vbo = Buffer::new(gl::ARRAY_BUFFER);
+            shape_vbo.set_data::<Vertex>(&[], gl::DYNAMIC_DRAW);
+            set_attribute!(shape_vao, 0, Vertex::0);
+            set_attribute!(shape_vao, 1, Vertex::1);
+
+            let circle_vao = VertexArray::new();
+            let corner_vbo = Buffer::new(gl::ARRAY_BUFFER);
+            corner_vbo.set_data(&QUAD_CORNERS, gl::STATIC_DRAW);
+            circle_vao.set_attribute::<[f32; 2]>(0, 2, 0);
+            let instance_vbo = Buffer::new(gl::ARRAY_BUFFER);
+            instance_vbo.set_data::<CircleInstance>(&[], gl::DYNAMIC_DRAW);
+            set_attribute!(circle_vao, 1, CircleInstance::0);
+            set_attribute!(circle_vao, 2, CircleInstance::1);
+            set_attribute!(circle_vao, 3, CircleInstance::2);
+            for attribute in 1..4{
+                gl::VertexAttribDivisor(attribute, 1);
+            }
+            gl::BindVertexArray(0);
+
+            Ok(Self{
+                world_mat: Mat4::identity(),
+                view: Vec2::new(2.0, 2.0),
+                shape_program,
+                circle_program,
+                shape_vao,
+                shape_vbo,
+                circle_vao,
+                _corner_vbo: corner_vbo,
+                instance_vbo,
+                triangles: Vec::new(),
+                lines: Vec::new(),
+                circles: Vec::new(),
+            })
+        }
+    }
+
+    //world units with the origin in the bottom left corner of the window, like Container::window
+    pub fn set_view(&mut self, width: i32, height: i32, pixels_per_unit: f32){
+        self.view = Vec2::new(width as f32 / pixels_per_unit, height as f32 / pixels_per_unit);
+        let mut world_mat = Mat4::identity();
+        world_mat.ortho(self.view.x, self.view.y);
+        self.world_mat = world_mat;
+    }
+
+    pub fn view_size(&self) -> Vec2{
+        self.view
+    }
+
+    pub fn line(&mut self, a: Vec2, b: Vec2, color: Rgba){
+        self.lines.push(Vertex([a.x, a.y], color));
+        self.lines.push(Vertex([b.x, b.y], color));
+    }
+
+    pub fn polyline(&mut self, points: &[Vec2], color: Rgba, closed: bool){
+        for pair in points.windows(2){
+            self.line(pair[0], pair[1], color);
+        }
+        if closed && points.len() > 2{
+            self.line(points[points.len() - 1], points[0], color);
+        }
+    }
+
+    pub fn triangle(&mut self, a: Vec2, b: Vec2, c: Vec2, color: Rgba){
+        for p in [a, b, c]{
+            self.triangles.push(Vertex([p.x, p.y], color));
+        }
+    }
+
+    //fanned out from the first vertex, so only right for convex polygons
+    pub fn convex_polygon(&mut self, vertices: &[Vec2], color: Rgba){
+        for i in 1..vertices.len().saturating_sub(1){
+            self.triangle(vertices[0], vertices[i], vertices[i + 1], color);
+        }
+    }
+
+    pub fn rect(&mut self, min: Vec2, max: Vec2, color: Rgba){
+        self.convex_polygon(&[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)], color);
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: Rgba){
+        self.circles.push(CircleInstance([center.x, center.y], radius, color));
+    }
+
+    pub fn shape(&mut self, shape: &Shape, position: Vec2, angle: f32, color: Rgba){
+        match shape{
+            Shape::Circle{ radius } => {
+                self.circle(position, *radius, color);
+                //a spoke so rolling can be seen
+                self.line(position, position + Vec2::new(*radius, 0.0).rotate(angle), OUTLINE);
+            },
+            Shape::Polygon{ .. } => {
+                let vertices = shape.world_vertices(position, angle);
+                self.convex_polygon(&vertices, color);
+                self.polyline(&vertices, OUTLINE, true);
+            },
+        }
+    }
+
+    pub fn body(&mut self, body: &Body){
+        let alpha = if body.is_sensor{
+            0.25
+        } else if body.is_dynamic() && !body.is_awake(){
+            SLEEPING_ALPHA
+        } else{
+            1.0
+        };
+        self.shape(&body.shape, body.position, body.angle, rgba(body.color, alpha));
+    }
+
+    //(x, y) samples as a line graph stretched over the rect. the y range always takes in zero so
+    //a value that barely moves draws as a flat line instead of noise blown up to the full height
+    pub fn graph(&mut self, samples: &[(f32, f32)], min: Vec2, max: Vec2, color: Rgba){
+        self.rect(min, max, GRAPH_BACKGROUND);
+        self.polyline(&[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)], OUTLINE, true);
+        let (first, last) = match (samples.first(), samples.last()){
+            (Some(first), Some(last)) if samples.len() > 1 => (first.0, last.0),
+            _ => return,
+        };
+        let (low, high) = samples.iter().fold((0.0_f32, 0.0_f32), |(low, high), (_, y)| (low.min(*y), high.max(*y)));
+        let x_span = (last - first).max(f32::EPSILON);
+        let y_span = (high - low).max(f32::EPSILON);
+        let size = max - min;
+        let points: Vec<Vec2> = samples.iter()
+            .map(|(x, y)| min + Vec2::new((x - first) / x_span * size.x, (y - low) / y_span * size.y))
+            .collect();
+        //where zero is, when the values go negative
+        if low < 0.0{
+            let zero = min.y - low / y_span * size.y;
+            self.line(Vec2::new(min.x, zero), Vec2::new(max.x, zero), OUTLINE);
+        }
+        self.polyline(&points, color, false);
+    }
+
+    //filled hull over the particles, the particles themselves are drawn with the world
+    pub fn soft_body(&mut self, soft_body: &SoftBody, world: &World, color: Rgba){
+        for [a, b, c] in soft_body.hull_triangles(world){
+            self.triangle(a, b, c, color);
+        }
+        self.polyline(&soft_body.hull_polygon(world), OUTLINE, true);
+    }
+
+    //smooth line through the segments, over the segments drawn with the world
+    pub fn rope(&mut self, rope: &Rope, world: &World, color: Rgba){
+        self.polyline(&rope.polyline(world, 4), color, false);
+    }
+
+    //one instanced circle per particle
+    pub fn fluid(&mut self, fluid: &Fluid){
+        for instance in fluid.instances(){
+            self.circle(instance.position, instance.radius, rgba(instance.color, FLUID_ALPHA));
+        }
+    }
+
+    pub fn world(&mut self, world: &World){
+        for (_, body) in world.bodies(){
+            self.body(body);
+        }
+        for fluid in world.fluids(){
+            self.fluid(fluid);
+        }
+        if let Some(periodic) = &world.periodic{
+            let (min, max) = (periodic.bounds.min, periodic.bounds.max);
+            self.polyline(&[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)], OUTLINE, true);
+        }
+        for (handle, position) in world.ghost_positions(){
+            if let Some(body) = world.body(handle){
+                self.shape(&body.shape, position, body.angle, rgba(body.color, GHOST_ALPHA));
+            }
+        }
+    }
+
+    //triangles first, circles over them and lines over everything
+    pub fn flush(&mut self){
+        unsafe{
+            if !self.triangles.is_empty() || !self.lines.is_empty(){
+                self.shape_program.set_uniform_mat4("u_world_mat", self.world_mat.raw());
+                self.shape_vao.bind();
+                if !self.triangles.is_empty(){
+                    self.shape_vbo.set_data(&self.triangles, gl::DYNAMIC_DRAW);
+                    gl::DrawArrays(gl::TRIANGLES, 0, self.triangles.len() as i32);
+                }
+            }
+            if !self.circles.is_empty(){
+                self.circle_program.set_uniform_mat4("u_world_mat", self.world_mat.raw());
+                self.circle_vao.bind();
+                self.instance_vbo.set_data(&self.circles, gl::DYNAMIC_DRAW);
+                gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, QUAD_CORNERS.len() as i32, self.circles.len() as i32);
+            }
+            if !self.lines.is_empty(){
+                self.shape_program.apply();
+                self.shape_vao.bind();
+                self.shape_vbo.set_data(&self.lines, gl::DYNAMIC_DRAW);
+                gl::DrawArrays(gl::LINES, 0, self.lines.len() as i32);
+            }
+            gl::BindVertexArray(0);
+        }
+        self.triangles.clear();
+        self.lines.clear();
+        self.circles.clear();
+    }
+}
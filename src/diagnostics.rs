@@ -0,0 +1,354 @@
+use std::collections::VecDeque;
+
+use thiserror::Error;
+
+use crate::math::Vec2;
+use crate::world::World;
+
+//watches quantities that should stay constant while the world runs, a closed system with no
+//friction, drag or thermostat should keep all of them, so drift points at integrator or solver bugs
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Invariants{
+    pub kinetic_energy: f32,
+    pub potential_energy: f32,
+    pub momentum: Vec2,
+    pub angular_momentum: f32,
+}
+
+impl Invariants{
+    pub fn measure(world: &mut World, origin: Vec2) -> Self{
+        Self{
+            kinetic_energy: world.kinetic_energy(),
+            potential_energy: world.potential_energy(),
+            momentum: world.linear_momentum(),
+            angular_momentum: world.angular_momentum(origin),
+        }
+    }
+
+    pub fn total_energy(&self) -> f32{
+        self.kinetic_energy + self.potential_energy
+    }
+}
+
+//drift relative to the values at the last reset, see ConservationMonitor::reset for the scales
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Drift{
+    pub energy: f32,
+    pub momentum: f32,
+    pub angular_momentum: f32,
+}
+
+impl Drift{
+    fn max(self, other: Self) -> Self{
+        Self{
+            energy: self.energy.max(other.energy),
+            momentum: self.momentum.max(other.momentum),
+            angular_momentum: self.angular_momentum.max(other.angular_momentum),
+        }
+    }
+}
+
+//relative drift allowed before an alarm goes off, None turns the check off
+//(e.g. momentum when there are walls, they push back without being part of the totals)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerances{
+    pub energy: Option<f32>,
+    pub momentum: Option<f32>,
+    pub angular_momentum: Option<f32>,
+}
+
+impl Default for Tolerances{
+    fn default() -> Self{
+        Self{
+            energy: Some(0.01),
+            momentum: Some(0.001),
+            angular_momentum: Some(0.001),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Error)]
+pub enum ConservationViolation{
+    #[error("Energy drifted by {drift} (tolerance {tolerance}) at t = {time}")]
+    Energy{ time: f32, drift: f32, tolerance: f32 },
+    #[error("Momentum drifted by {drift} (tolerance {tolerance}) at t = {time}")]
+    Momentum{ time: f32, drift: f32, tolerance: f32 },
+    #[error("Angular momentum drifted by {drift} (tolerance {tolerance}) at t = {time}")]
+    AngularMomentum{ time: f32, drift: f32, tolerance: f32 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sample{
+    pub time: f32,
+    pub invariants: Invariants,
+    pub drift: Drift,
+}
+
+//what to plot from the history
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantity{
+    KineticEnergy,
+    PotentialEnergy,
+    TotalEnergy,
+    Momentum,
+    AngularMomentum,
+    EnergyDrift,
+    MomentumDrift,
+    AngularMomentumDrift,
+}
+
+pub struct ConservationMonitor{
+    //angular momentum is measured around this point
+    pub origin: Vec2,
+    pub tolerances: Tolerances,
+    //how many samples are kept for the graph, 600 is ten seconds at 60 steps per second
+    pub history_length: usize,
+    initial: Invariants,
+    latest: Invariants,
+    energy_scale: f32,
+    momentum_scale: f32,
+    angular_momentum_scale: f32,
+    elapsed: f32,
+    worst: Drift,
+    history: VecDeque<Sample>,
+    //alarms raised by the last sample
+    violations: Vec<ConservationViolation>,
+    first_violation: Option<ConservationViolation>,
+}
+
+impl ConservationMonitor{
+    pub fn new(world: &mut World) -> Self{
+        let mut monitor = Self{
+            origin: Vec2::zero(),
+            tolerances: Tolerances::default(),
+            history_length: 600,
+            initial: Invariants::default(),
+            latest: Invariants::default(),
+            energy_scale: 1.0,
+            momentum_scale: 1.0,
+            angular_momentum_scale: 1.0,
+            elapsed: 0.0,
+            worst: Drift::default(),
+            history: VecDeque::new(),
+            violations: Vec::new(),
+            first_violation: None,
+        };
+        monitor.reset(world);
+        monitor
+    }
+
+    //takes the current state as the reference
+    pub fn reset(&mut self, world: &mut World){
+        self.initial = Invariants::measure(world, self.origin);
+        self.latest = self.initial;
+
+        //the totals can sit at or near zero (a gas at rest, a bound orbit) so drift is measured
+        //against the sum of the magnitudes instead, that way it stays a sensible fraction
+        let (mut momentum, mut angular_momentum) = (0.0, 0.0);
+        for (_, body) in world.bodies().filter(|(_, body)| body.is_dynamic()){
+            momentum += body.mass() * body.velocity.length();
+            angular_momentum += ((body.position - self.origin).cross(body.velocity * body.mass())).abs()
+                + (body.inertia() * body.angular_velocity).abs();
+        }
+        let scale = |value: f32| if value > f32::EPSILON { value } else { 1.0 };
+        self.energy_scale = scale(self.initial.total_energy().abs().max(self.initial.kinetic_energy));
+        self.momentum_scale = scale(momentum);
+        self.angular_momentum_scale = scale(angular_momentum);
+
+        self.elapsed = 0.0;
+        self.worst = Drift::default();
+        self.history.clear();
+        self.violations.clear();
+        self.first_violation = None;
+    }
+
+    //call once after every world step, returns the alarms raised by this step
+    pub fn sample(&mut self, world: &mut World, dt: f32) -> &[ConservationViolation]{
+        self.elapsed += dt;
+        self.latest = Invariants::measure(world, self.origin);
+        let drift = self.drift();
+        self.worst = self.worst.max(drift);
+
+        self.violations.clear();
+        let time = self.elapsed;
+        if let Some(tolerance) = self.tolerances.energy.filter(|t| drift.energy > *t){
+            self.violations.push(ConservationViolation::Energy{ time, drift: drift.energy, tolerance });
+        }
+        if let Some(tolerance) = self.tolerances.momentum.filter(|t| drift.momentum > *t){
+            self.violations.push(ConservationViolation::Momentum{ time, drift: drift.momentum, tolerance });
+        }
+        if let Some(tolerance) = self.tolerances.angular_momentum.filter(|t| drift.angular_momentum > *t){
+            self.violations.push(ConservationViolation::AngularMomentum{ time, drift: drift.angular_momentum, tolerance });
+        }
+        if self.first_violation.is_none(){
+            self.first_violation = self.violations.first().copied();
+        }
+
+        self.history.push_back(Sample{
+            time,
+            invariants: self.latest,
+            drift,
+        });
+        while self.history.len() > self.history_length{
+            self.history.pop_front();
+        }
+        &self.violations
+    }
+
+    pub fn initial(&self) -> &Invariants{
+        &self.initial
+    }
+
+    pub fn latest(&self) -> &Invariants{
+        &self.latest
+    }
+
+    pub fn drift(&self) -> Drift{
+        Drift{
+            energy: (self.latest.total_energy() - self.initial.total_energy()).abs() / self.energy_scale,
+            momentum: (self.latest.momentum - self.initial.momentum).length() / self.momentum_scale,
+            angular_momentum: (self.latest.angular_momentum - self.initial.angular_momentum).abs() / self.angular_momentum_scale,
+        }
+    }
+
+    //largest drift seen since the last reset
+    pub fn worst_drift(&self) -> Drift{
+        self.worst
+    }
+
+    pub fn violations(&self) -> &[ConservationViolation]{
+        &self.violations
+    }
+
+    //Err with the first alarm since the last reset, meant for tests
+    pub fn check(&self) -> Result<(), ConservationViolation>{
+        match self.first_violation{
+            Some(violation) => Err(violation),
+            None => Ok(()),
+        }
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &Sample>{
+        self.history.iter()
+    }
+
+    //(time, value) pairs ready to be drawn as a line graph
+    pub fn series(&self, quantity: Quantity) -> Vec<(f32, f32)>{
+        self.history.iter().map(|sample| {
+            let invariants = &sample.invariants;
+            let value = match quantity{
+                Quantity::KineticEnergy => invariants.kinetic_energy,
+                Quantity::PotentialEnergy => invariants.potential_energy,
+                Quantity::TotalEnergy => invariants.total_energy(),
+                Quantity::Momentum => invariants.momentum.length(),
+                Quantity::AngularMomentum => invariants.angular_momentum,
+                Quantity::EnergyDrift => sample.drift.energy,
+                Quantity::MomentumDrift => sample.drift.momentum,
+                Quantity::AngularMomentumDrift => sample.drift.angular_momentum,
+            };
+            (sample.time, value)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::body::Body;
+    use crate::restitution::RestitutionModel;
+    use crate::shape::Shape;
+
+    const DT: f32 = 1.0 / 120.0;
+    //position correction pushes overlapping bodies apart without touching their velocities,
+    //which moves the lever arms a little, so angular momentum gets a bit more room than momentum
+    const POSITION_CORRECTION_TOLERANCE: f32 = 0.005;
+
+    fn elastic_world() -> World{
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        world.restitution_model = RestitutionModel::Threshold{ threshold: 0.0 };
+        world
+    }
+
+    fn elastic(mut body: Body, velocity: Vec2) -> Body{
+        body.velocity = velocity;
+        body.restitution = 1.0;
+        body.friction = 0.0;
+        body.static_friction = 0.0;
+        body
+    }
+
+    fn run(world: &mut World, monitor: &mut ConservationMonitor, steps: usize){
+        for _ in 0..steps{
+            world.step(DT);
+            monitor.sample(world, DT);
+        }
+    }
+
+    #[test]
+    fn elastic_circle_collisions_conserve_momentum_and_energy(){
+        let mut world = elastic_world();
+        //head on, glancing and a light one into a heavy one
+        world.add_body(elastic(Body::new(Shape::circle(0.5), Vec2::new(0.0, 0.0)), Vec2::new(3.0, 0.0)));
+        world.add_body(elastic(Body::new(Shape::circle(0.5), Vec2::new(4.0, 0.3)), Vec2::new(-2.0, 0.0)));
+        world.add_body(elastic(Body::new(Shape::circle(0.3), Vec2::new(2.0, 4.0)), Vec2::new(0.5, -4.0)));
+        world.add_body(elastic(Body::new(Shape::circle(1.0), Vec2::new(-3.0, 3.0)), Vec2::new(1.0, -1.0)));
+        let mut monitor = ConservationMonitor::new(&mut world);
+        monitor.tolerances.angular_momentum = Some(POSITION_CORRECTION_TOLERANCE);
+        run(&mut world, &mut monitor, 240);
+        monitor.check().unwrap();
+        //the bodies really did meet, otherwise there was nothing to conserve
+        let initial: Vec<Vec2> = vec![Vec2::new(3.0, 0.0), Vec2::new(-2.0, 0.0)];
+        let velocities: Vec<Vec2> = world.bodies().take(2).map(|(_, body)| body.velocity).collect();
+        assert!(velocities != initial);
+    }
+
+    #[test]
+    fn frictionless_spinning_body_conserves_angular_momentum(){
+        let mut world = elastic_world();
+        let mut spinner = elastic(Body::new(Shape::rect(2.0, 0.5), Vec2::new(0.0, 0.0)), Vec2::new(1.0, 0.0));
+        spinner.angular_velocity = 6.0;
+        let spinner = world.add_body(spinner);
+        world.add_body(elastic(Body::new(Shape::circle(0.4), Vec2::new(3.0, 0.2)), Vec2::new(-1.0, 0.0)));
+        let mut monitor = ConservationMonitor::new(&mut world);
+        monitor.origin = Vec2::new(1.0, -2.0);
+        monitor.tolerances.angular_momentum = Some(POSITION_CORRECTION_TOLERANCE);
+        monitor.reset(&mut world);
+        run(&mut world, &mut monitor, 240);
+        monitor.check().unwrap();
+        //the hits traded spin for motion, so there was something to get wrong
+        assert!((world.body(spinner).unwrap().angular_velocity - 6.0).abs() > 0.1);
+    }
+
+    #[test]
+    fn drift_past_the_tolerance_raises_an_alarm(){
+        let mut world = elastic_world();
+        let handle = world.add_body(Body::new(Shape::circle(0.5), Vec2::zero()));
+        let mut monitor = ConservationMonitor::new(&mut world);
+        run(&mut world, &mut monitor, 10);
+        monitor.check().unwrap();
+        world.body_mut(handle).unwrap().velocity = Vec2::new(1.0, 0.0);
+        let violations = monitor.sample(&mut world, DT).to_vec();
+        assert!(violations.iter().any(|v| matches!(v, ConservationViolation::Energy{ .. })));
+        assert!(violations.iter().any(|v| matches!(v, ConservationViolation::Momentum{ .. })));
+        //the first alarm sticks around after things settle
+        world.body_mut(handle).unwrap().velocity = Vec2::zero();
+        monitor.sample(&mut world, DT);
+        assert!(monitor.violations().is_empty());
+        assert!(matches!(monitor.check(), Err(ConservationViolation::Energy{ .. })));
+    }
+
+    #[test]
+    fn history_keeps_the_latest_samples(){
+        let mut world = elastic_world();
+        world.add_body(elastic(Body::new(Shape::circle(0.5), Vec2::zero()), Vec2::new(2.0, 0.0)));
+        let mut monitor = ConservationMonitor::new(&mut world);
+        monitor.history_length = 50;
+        run(&mut world, &mut monitor, 80);
+        let series = monitor.series(Quantity::KineticEnergy);
+        assert_eq!(series.len(), 50);
+        assert!((series[0].0 - 31.0 * DT).abs() < 1e-4);
+        assert!(series.iter().all(|(_, energy)| (energy - monitor.initial().kinetic_energy).abs() < 1e-3));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::body::Body;
+use crate::broadphase::SpatialGrid;
+use crate::force::{dynamic_bodies, ForceGenerator};
+use crate::math::Vec2;
+use crate::periodic::{self, PeriodicBounds};
+use crate::shape::Aabb;
+
+//coulomb interaction between every pair of charged bodies, like charges repel. in a periodic
+//world it is minimum image only, there is no ewald sum so keep the cutoff under half the box
+pub struct Coulomb{
+    pub coulomb_constant: f32,
+    pub softening: f32,
+    //pairs further apart than this are ignored, f32::INFINITY (or 0) for no cutoff
+    pub cutoff: f32,
+    grid: SpatialGrid,
+}
+
+impl Coulomb{
+    pub fn new(coulomb_constant: f32) -> Self{
+        Self{
+            coulomb_constant,
+            softening: 0.05,
+            cutoff: f32::INFINITY,
+            grid: SpatialGrid::new(1.0),
+        }
+    }
+
+    pub fn with_cutoff(mut self, cutoff: f32) -> Self{
+        self.cutoff = cutoff;
+        self
+    }
+
+    fn has_cutoff(&self) -> bool{
+        self.cutoff.is_finite() && self.cutoff > 0.0
+    }
+
+    //force on the body at `offset` from the other one
+    fn pair_force(&self, offset: Vec2, charge_product: f32) -> Vec2{
+        let dist_sq = offset.length_squared() + self.softening * self.softening;
+        if dist_sq <= 0.0{
+            return Vec2::zero();
+        }
+        offset * (self.coulomb_constant * charge_product / (dist_sq * dist_sq.sqrt()))
+    }
+
+    fn pair_energy(&self, distance: f32, charge_product: f32) -> f32{
+        let softened = (distance * distance + self.softening * self.softening).sqrt();
+        if softened <= 0.0{
+            return 0.0;
+        }
+        self.coulomb_constant * charge_product / softened
+    }
+}
+
+impl ForceGenerator for Coulomb{
+    fn apply(&mut self, bodies: &mut [Option<Body>], periodic: &Option<PeriodicBounds>, _dt: f32){
+        let charged: Vec<usize> = bodies.iter().enumerate()
+            .filter(|(_, body)| body.as_ref().is_some_and(|body| body.charge != 0.0))
+            .map(|(i, _)| i)
+            .collect();
+        let position = |i: usize| bodies[i].as_ref().unwrap().position;
+        let charge = |i: usize| bodies[i].as_ref().unwrap().charge;
+
+        let mut forces = vec![Vec2::zero(); bodies.len()];
+        if self.has_cutoff(){
+            //only neighbours within the cutoff, found through a grid with cutoff sized cells
+            if self.grid.cell_size() != self.cutoff{
+                self.grid.set_cell_size(self.cutoff);
+            }
+            self.grid.clear();
+            for &i in charged.iter(){
+                let aabb = Aabb::new(position(i), position(i));
+                self.grid.insert(i, aabb);
+                if let Some(periodic) = periodic{
+                    //a point is never outside, so the copies have to cover everything within the cutoff of an edge
+                    let reach = Vec2::new(self.cutoff, self.cutoff);
+                    for offset in periodic.image_offsets(&Aabb::new(aabb.min - reach, aabb.max + reach)){
+                        self.grid.insert(i, Aabb::new(aabb.min + offset, aabb.max + offset));
+                    }
+                }
+            }
+            let cutoff_sq = self.cutoff * self.cutoff;
+            for &i in charged.iter(){
+                let reach = Vec2::new(self.cutoff, self.cutoff);
+                for j in self.grid.query(&Aabb::new(position(i) - reach, position(i) + reach)){
+                    let offset = periodic::separation(periodic, position(j), position(i));
+                    if j != i && offset.length_squared() <= cutoff_sq{
+                        forces[i] += self.pair_force(offset, charge(i) * charge(j));
+                    }
+                }
+            }
+        } else{
+            for (n, &i) in charged.iter().enumerate(){
+                for &j in charged[(n + 1)..].iter(){
+                    let force = self.pair_force(periodic::separation(periodic, position(j), position(i)), charge(i) * charge(j));
+                    forces[i] += force;
+                    forces[j] -= force;
+                }
+            }
+        }
+
+        for i in charged{
+            let body = bodies[i].as_mut().unwrap();
+            if body.is_dynamic() && body.is_awake(){
+                body.force += forces[i];
+            }
+        }
+    }
+
+    //shifted by the energy at the cutoff like the pair potentials, so pairs crossing it don't jump
+    fn potential_energy(&mut self, bodies: &[Option<Body>], periodic: &Option<PeriodicBounds>) -> f32{
+        let charged: Vec<&Body> = bodies.iter().flatten().filter(|body| body.charge != 0.0).collect();
+        let mut energy = 0.0;
+        for (n, a) in charged.iter().enumerate(){
+            for b in charged[(n + 1)..].iter(){
+                let distance = periodic::separation(periodic, a.position, b.position).length();
+                if self.has_cutoff(){
+                    if distance > self.cutoff{
+                        continue;
+                    }
+                    energy -= self.pair_energy(self.cutoff, a.charge * b.charge);
+                }
+                energy += self.pair_energy(distance, a.charge * b.charge);
+            }
+        }
+        energy
+    }
+}
+
+//uniform magnetic field pointing out of the screen (negative points into it)
+pub struct MagneticField{
+    pub strength: f32,
+}
+
+impl ForceGenerator for MagneticField{
+    //NOTE: instead of adding q v x B as a force the velocity is rotated by the exact
+    //cyclotron angle, otherwise explicit integration makes the orbits spiral outwards
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, dt: f32){
+        for body in bodies.iter_mut().flatten(){
+            if !body.is_dynamic() || !body.is_awake() || body.charge == 0.0 || body.inv_mass() == 0.0{
+                continue;
+            }
+            let cyclotron_frequency = body.charge * self.strength * body.inv_mass();
+            body.velocity = body.velocity.rotate(-cyclotron_frequency * dt);
+        }
+    }
+}
+
+//uniform electric field, pushes positive charges along it
+pub struct ElectricField{
+    pub field: Vec2,
+}
+
+impl ForceGenerator for ElectricField{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, _dt: f32){
+        for body in bodies.iter_mut().flatten(){
+            if body.is_dynamic() && body.is_awake(){
+                body.force += self.field * body.charge;
+            }
+        }
+    }
+
+    fn potential_energy(&mut self, bodies: &[Option<Body>], _periodic: &Option<PeriodicBounds>) -> f32{
+        dynamic_bodies(bodies).map(|body| -body.charge * self.field.dot(body.position)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::body::BodyHandle;
+    use crate::shape::Shape;
+    use crate::world::World;
+
+    fn charged(world: &mut World, position: Vec2, charge: f32) -> BodyHandle{
+        let mut body = Body::new(Shape::circle(0.1), position);
+        body.charge = charge;
+        world.add_body(body)
+    }
+
+    #[test]
+    fn charge_in_a_magnetic_field_orbits_back_to_the_start(){
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        let particle = charged(&mut world, Vec2::zero(), 1.0);
+        let mass = world.body(particle).unwrap().mass();
+        world.body_mut(particle).unwrap().velocity = Vec2::new(1.0, 0.0);
+        //cyclotron frequency of 2, so a full circle of radius 0.5 takes pi seconds
+        world.add_force_generator(Box::new(MagneticField{ strength: 2.0 * mass }));
+        let dt = 1.0 / 600.0;
+        let steps = (std::f32::consts::PI / dt).round() as usize;
+        let mut lowest = 0.0f32;
+        for _ in 0..steps{
+            world.step(dt);
+            lowest = lowest.min(world.body(particle).unwrap().position.y);
+        }
+        let body = world.body(particle).unwrap();
+        assert!(body.position.length() < 0.01, "{:?}", body.position);
+        assert!((body.velocity.length() - 1.0).abs() < 1e-4);
+        //positive charge in a field out of the screen turns clockwise
+        assert!((lowest + 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn like_charges_repel_and_opposite_ones_attract(){
+        for (charge, repels) in [(1.0, true), (-1.0, false)]{
+            let mut world = World::new(Vec2::zero());
+            let p = charged(&mut world, Vec2::zero(), 1.0);
+            let q = charged(&mut world, Vec2::new(1.0, 0.0), charge);
+            let far = charged(&mut world, Vec2::new(10.0, 0.0), 1.0);
+            world.add_force_generator(Box::new(Coulomb::new(1.0).with_cutoff(2.0)));
+            world.step(0.01);
+            let (vp, vq) = (world.body(p).unwrap().velocity.x, world.body(q).unwrap().velocity.x);
+            assert_eq!(vp < 0.0 && vq > 0.0, repels);
+            assert!((vp + vq).abs() < 1e-6);
+            assert_eq!(world.body(far).unwrap().velocity, Vec2::zero());
+        }
+    }
+
+    #[test]
+    fn zero_cutoff_sums_every_pair(){
+        let mut bodies: Vec<Option<Body>> = [(Vec2::zero(), 1.0), (Vec2::new(1.0, 0.0), -2.0), (Vec2::new(0.0, 30.0), 1.0)].into_iter()
+            .map(|(position, charge)| {
+                let mut body = Body::new(Shape::circle(0.1), position);
+                body.charge = charge;
+                Some(body)
+            })
+            .collect();
+        let mut expected = bodies.clone();
+        Coulomb::new(1.0).apply(&mut expected, &None, 0.01);
+        let mut coulomb = Coulomb::new(1.0).with_cutoff(0.0);
+        coulomb.apply(&mut bodies, &None, 0.01);
+        for (body, expected) in bodies.iter().flatten().zip(expected.iter().flatten()){
+            assert_eq!(body.force, expected.force);
+            assert!(body.force != Vec2::zero());
+        }
+        assert_eq!(coulomb.potential_energy(&bodies, &None), Coulomb::new(1.0).potential_energy(&bodies, &None));
+    }
+
+    #[test]
+    fn coulomb_force_is_the_slope_of_its_energy(){
+        let coulomb = Coulomb::new(2.0);
+        let (r, h) = (0.7, 1e-3);
+        let slope = (coulomb.pair_energy(r + h, -1.5) - coulomb.pair_energy(r - h, -1.5)) / (2.0 * h);
+        let force = coulomb.pair_force(Vec2::new(r, 0.0), -1.5);
+        assert!((force.x + slope).abs() < 1e-2);
+    }
+
+    #[test]
+    fn electric_field_pushes_along_the_charge_sign(){
+        let mut bodies = vec![Some(Body::new(Shape::circle(0.1), Vec2::zero())), Some(Body::new(Shape::circle(0.1), Vec2::zero()))];
+        bodies[0].as_mut().unwrap().charge = 2.0;
+        bodies[1].as_mut().unwrap().charge = -1.0;
+        let mut field = ElectricField{ field: Vec2::new(0.0, 3.0) };
+        field.apply(&mut bodies, &None, 0.01);
+        assert_eq!(bodies[0].as_ref().unwrap().force, Vec2::new(0.0, 6.0));
+        assert_eq!(bodies[1].as_ref().unwrap().force, Vec2::new(0.0, -3.0));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::body::{Body, BodyHandle};
+use crate::collision::Manifold;
+use crate::math::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContactEvent{
+    pub a: BodyHandle,
+    pub b: BodyHandle,
+    //average of the contact points
+    pub point: Vec2,
+    //from a to b
+    pub normal: Vec2,
+    //closing speed along the normal before the solver ran
+    pub relative_speed: f32,
+    //total normal impulse the solver applied this step
+    pub impulse: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CollisionEvent{
+    Begin(ContactEvent),
+    End{
+        a: BodyHandle,
+        b: BodyHandle,
+    },
+}
+
+//every hook has an empty default so listeners only implement what they need
+pub trait ContactListener{
+    fn begin_contact(&mut self, _event: &ContactEvent){}
+
+    fn end_contact(&mut self, _a: BodyHandle, _b: BodyHandle){}
+
+    //called before the solver sees the contact, returning false skips it for this step
+    fn pre_solve(&mut self, _manifold: &Manifold, _a: &Body, _b: &Body) -> bool{
+        true
+    }
+
+    fn post_solve(&mut self, _event: &ContactEvent){}
+}
+
+pub fn contact_event(manifold: &Manifold, relative_speed: f32) -> ContactEvent{
+    let point = manifold.points.iter().fold(Vec2::zero(), |acc, p| acc + p.point) / manifold.points.len().max(1) as f32;
+    ContactEvent{
+        a: manifold.a,
+        b: manifold.b,
+        point,
+        normal: manifold.normal,
+        relative_speed,
+        impulse: manifold.total_normal_impulse(),
+    }
+}
+
+//closing speed of the two bodies at the contact, positive when they move towards each other
+pub fn closing_speed(manifold: &Manifold, a: &Body, b: &Body) -> f32{
+    manifold.points.iter()
+        .map(|p| -(b.velocity_at(p.point - manifold.offset) - a.velocity_at(p.point)).dot(manifold.normal))
+        .fold(0.0, f32::max)
+}
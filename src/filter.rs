@@ -0,0 +1,75 @@
+use crate::body::{Body, BodyHandle};
+
+//returns false to stop the two bodies from colliding
+pub type PairFilter = Box<dyn Fn(BodyHandle, &Body, BodyHandle, &Body) -> bool>;
+
+pub const ALL_CATEGORIES: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionFilter{
+    //which layers the body is on
+    pub category: u32,
+    //which layers the body collides with
+    pub mask: u32,
+    //bodies sharing a positive group always collide, sharing a negative one never do,
+    //0 means no group and falls back to category/mask
+    pub group: i32,
+}
+
+impl Default for CollisionFilter{
+    fn default() -> Self{
+        Self{
+            category: 1,
+            mask: ALL_CATEGORIES,
+            group: 0,
+        }
+    }
+}
+
+impl CollisionFilter{
+    pub fn new(category: u32, mask: u32) -> Self{
+        Self{
+            category,
+            mask,
+            group: 0,
+        }
+    }
+
+    pub fn with_group(mut self, group: i32) -> Self{
+        self.group = group;
+        self
+    }
+
+    pub fn should_collide(&self, other: &CollisionFilter) -> bool{
+        if self.group != 0 && self.group == other.group{
+            return self.group > 0;
+        }
+        (self.mask & other.category) != 0 && (other.mask & self.category) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn category_has_to_be_in_both_masks(){
+        let player = CollisionFilter::new(0b001, 0b110);
+        let enemy = CollisionFilter::new(0b010, 0b001);
+        let pickup = CollisionFilter::new(0b100, 0b000);
+        assert!(player.should_collide(&enemy) && enemy.should_collide(&player));
+        //the player wants pickups but pickups don't want anything
+        assert!(!player.should_collide(&pickup) && !pickup.should_collide(&player));
+        assert!(!enemy.should_collide(&enemy));
+    }
+
+    #[test]
+    fn shared_group_overrides_masks(){
+        let nothing = CollisionFilter::new(1, 0);
+        assert!(nothing.with_group(3).should_collide(&nothing.with_group(3)));
+        assert!(!CollisionFilter::default().with_group(-2).should_collide(&CollisionFilter::default().with_group(-2)));
+        //different groups fall back to the masks
+        assert!(CollisionFilter::default().with_group(-2).should_collide(&CollisionFilter::default().with_group(-3)));
+        assert!(!nothing.with_group(3).should_collide(&nothing.with_group(4)));
+    }
+}
@@ -0,0 +1,245 @@
+use crate::body::{Body, BodyHandle};
+use crate::math::Vec2;
+use crate::periodic::{self, PeriodicBounds};
+
+//runs every step before integration and adds to body.force / body.torque,
+//bodies are indexed by their handle. periodic is the world's, anything that depends on
+//where bodies are relative to each other or to a point goes through the minimum image
+pub trait ForceGenerator{
+    fn apply(&mut self, bodies: &mut [Option<Body>], periodic: &Option<PeriodicBounds>, dt: f32);
+
+    //energy stored in the field, generators that don't come from a potential (drag, wind...) leave this at 0
+    fn potential_energy(&mut self, _bodies: &[Option<Body>], _periodic: &Option<PeriodicBounds>) -> f32{
+        0.0
+    }
+
+    //called when a body leaves the world, before its slot can be reused. generators tied to
+    //particular bodies return false to be dropped along with them
+    fn body_removed(&mut self, _handle: BodyHandle) -> bool{
+        true
+    }
+}
+
+//only awake dynamic bodies get pushed around
+pub fn affected(bodies: &mut [Option<Body>]) -> impl Iterator<Item = &mut Body>{
+    bodies.iter_mut().flatten().filter(|body| body.is_dynamic() && body.is_awake())
+}
+
+//sleeping bodies still have energy, so potential energy is summed over all of them
+pub fn dynamic_bodies(bodies: &[Option<Body>]) -> impl Iterator<Item = &Body>{
+    bodies.iter().flatten().filter(|body| body.is_dynamic())
+}
+
+pub struct UniformGravity{
+    pub acceleration: Vec2,
+}
+
+impl ForceGenerator for UniformGravity{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, _dt: f32){
+        for body in affected(bodies){
+            body.force += self.acceleration * body.mass();
+        }
+    }
+
+    fn potential_energy(&mut self, bodies: &[Option<Body>], _periodic: &Option<PeriodicBounds>) -> f32{
+        dynamic_bodies(bodies).map(|body| -body.mass() * self.acceleration.dot(body.position)).sum()
+    }
+}
+
+//pulls bodies towards a point with inverse square falloff, negative strength pushes them away
+pub struct PointAttractor{
+    pub center: Vec2,
+    pub strength: f32,
+    //closer than this the force stops growing
+    pub min_distance: f32,
+    //bodies further away are not affected, f32::INFINITY for no limit
+    pub max_distance: f32,
+}
+
+impl PointAttractor{
+    pub fn new(center: Vec2, strength: f32) -> Self{
+        Self{
+            center,
+            strength,
+            min_distance: 0.1,
+            max_distance: f32::INFINITY,
+        }
+    }
+}
+
+impl ForceGenerator for PointAttractor{
+    fn apply(&mut self, bodies: &mut [Option<Body>], periodic: &Option<PeriodicBounds>, _dt: f32){
+        for body in affected(bodies){
+            let offset = periodic::separation(periodic, body.position, self.center);
+            let distance = offset.length();
+            if distance > self.max_distance || distance < f32::EPSILON{
+                continue;
+            }
+            let distance = distance.max(self.min_distance);
+            body.force += offset.normalize() * (self.strength * body.mass() / (distance * distance));
+        }
+    }
+
+    //inside min_distance the pull is constant so the potential goes on linearly from there
+    fn potential_energy(&mut self, bodies: &[Option<Body>], periodic: &Option<PeriodicBounds>) -> f32{
+        let mut energy = 0.0;
+        for body in dynamic_bodies(bodies){
+            let distance = periodic::separation(periodic, body.position, self.center).length();
+            if distance > self.max_distance{
+                continue;
+            }
+            let edge = -self.strength * body.mass() / self.min_distance;
+            energy += if distance >= self.min_distance{
+                -self.strength * body.mass() / distance
+            } else{
+                edge - (self.min_distance - distance) * self.strength * body.mass() / (self.min_distance * self.min_distance)
+            };
+        }
+        energy
+    }
+}
+
+//force proportional to velocity, also slows down spinning
+pub struct LinearDrag{
+    pub coefficient: f32,
+    pub angular_coefficient: f32,
+}
+
+impl ForceGenerator for LinearDrag{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, _dt: f32){
+        for body in affected(bodies){
+            body.force -= body.velocity * self.coefficient;
+            body.torque -= body.angular_velocity * self.angular_coefficient;
+        }
+    }
+}
+
+//force proportional to the square of the speed, like air resistance at higher speeds
+pub struct QuadraticDrag{
+    pub coefficient: f32,
+}
+
+impl ForceGenerator for QuadraticDrag{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, _dt: f32){
+        for body in affected(bodies){
+            body.force -= body.velocity * (self.coefficient * body.velocity.length());
+        }
+    }
+}
+
+//drag towards the wind velocity instead of towards rest
+pub struct Wind{
+    pub velocity: Vec2,
+    pub coefficient: f32,
+}
+
+impl ForceGenerator for Wind{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, _dt: f32){
+        for body in affected(bodies){
+            body.force += (self.velocity - body.velocity) * self.coefficient;
+        }
+    }
+}
+
+//swirls bodies around a point, positive strength turns counter-clockwise
+pub struct Vortex{
+    pub center: Vec2,
+    pub strength: f32,
+    //outside of this radius the vortex does nothing
+    pub radius: f32,
+    //pull towards the center so bodies don't get flung out
+    pub inward_strength: f32,
+}
+
+impl ForceGenerator for Vortex{
+    fn apply(&mut self, bodies: &mut [Option<Body>], periodic: &Option<PeriodicBounds>, _dt: f32){
+        for body in affected(bodies){
+            let offset = periodic::separation(periodic, self.center, body.position);
+            let distance = offset.length();
+            if distance > self.radius || distance < f32::EPSILON{
+                continue;
+            }
+            //strongest in the middle, fading out to nothing at the edge
+            let falloff = 1.0 - distance / self.radius;
+            let direction = offset / distance;
+            body.force += (direction.perp() * self.strength - direction * self.inward_strength) * (falloff * body.mass());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::shape::{Aabb, Shape};
+
+    fn bodies(positions: &[Vec2]) -> Vec<Option<Body>>{
+        positions.iter().map(|p| Some(Body::new(Shape::circle(0.5), *p))).collect()
+    }
+
+    fn force(bodies: &[Option<Body>], i: usize) -> Vec2{
+        bodies[i].as_ref().unwrap().force
+    }
+
+    #[test]
+    fn attractor_pulls_with_inverse_square(){
+        let mut bodies = bodies(&[Vec2::new(2.0, 0.0), Vec2::new(0.0, 4.0), Vec2::new(0.0, 0.01)]);
+        let mass = bodies[0].as_ref().unwrap().mass();
+        let mut attractor = PointAttractor::new(Vec2::zero(), 8.0);
+        attractor.apply(&mut bodies, &None, 0.01);
+        assert!((force(&bodies, 0) - Vec2::new(-2.0 * mass, 0.0)).length() < 1e-4);
+        assert!((force(&bodies, 1) - Vec2::new(0.0, -0.5 * mass)).length() < 1e-4);
+        //capped at min_distance
+        assert!((force(&bodies, 2).length() - 800.0 * mass).abs() < 1e-1);
+        //force is minus the slope of the potential
+        let energy = |x: f32, attractor: &mut PointAttractor| attractor.potential_energy(&[Some(Body::new(Shape::circle(0.5), Vec2::new(x, 0.0)))], &None);
+        let slope = (energy(2.001, &mut attractor) - energy(1.999, &mut attractor)) / 0.002;
+        assert!((slope - 2.0 * mass).abs() < 1e-2);
+    }
+
+    #[test]
+    fn attractor_pulls_across_periodic_edges(){
+        let periodic = Some(PeriodicBounds::new(Aabb::new(Vec2::zero(), Vec2::new(10.0, 10.0))));
+        let mut bodies = bodies(&[Vec2::new(9.0, 5.0), Vec2::new(3.0, 5.0)]);
+        let mut attractor = PointAttractor::new(Vec2::new(1.0, 5.0), 8.0);
+        attractor.apply(&mut bodies, &periodic, 0.01);
+        //2 away through the right edge, pulled to the right
+        let mass = bodies[0].as_ref().unwrap().mass();
+        assert!((force(&bodies, 0) - Vec2::new(2.0 * mass, 0.0)).length() < 1e-4);
+        assert!((force(&bodies, 1) - Vec2::new(-2.0 * mass, 0.0)).length() < 1e-4);
+        assert_eq!(attractor.potential_energy(&bodies, &periodic), -8.0 * mass);
+    }
+
+    #[test]
+    fn only_awake_dynamic_bodies_are_pushed(){
+        let mut bodies = bodies(&[Vec2::zero(), Vec2::zero(), Vec2::zero()]);
+        bodies[1].as_mut().unwrap().set_awake(false);
+        bodies[2] = Some(Body::new_static(Shape::circle(0.5), Vec2::zero()));
+        UniformGravity{ acceleration: Vec2::new(0.0, -10.0) }.apply(&mut bodies, &None, 0.01);
+        assert!(force(&bodies, 0).y < 0.0);
+        assert_eq!(force(&bodies, 1), Vec2::zero());
+        assert_eq!(force(&bodies, 2), Vec2::zero());
+    }
+
+    #[test]
+    fn drag_and_wind_oppose_relative_velocity(){
+        let mut bodies = bodies(&[Vec2::zero()]);
+        bodies[0].as_mut().unwrap().velocity = Vec2::new(2.0, 0.0);
+        bodies[0].as_mut().unwrap().angular_velocity = 1.0;
+        LinearDrag{ coefficient: 0.5, angular_coefficient: 0.1 }.apply(&mut bodies, &None, 0.01);
+        assert_eq!(force(&bodies, 0), Vec2::new(-1.0, 0.0));
+        assert_eq!(bodies[0].as_ref().unwrap().torque, -0.1);
+        QuadraticDrag{ coefficient: 0.5 }.apply(&mut bodies, &None, 0.01);
+        assert_eq!(force(&bodies, 0), Vec2::new(-3.0, 0.0));
+        Wind{ velocity: Vec2::new(2.0, 1.0), coefficient: 1.0 }.apply(&mut bodies, &None, 0.01);
+        assert_eq!(force(&bodies, 0), Vec2::new(-3.0, 1.0));
+    }
+
+    #[test]
+    fn vortex_turns_counter_clockwise_and_fades_out(){
+        let mut bodies = bodies(&[Vec2::new(1.0, 0.0), Vec2::new(3.0, 0.0)]);
+        Vortex{ center: Vec2::zero(), strength: 1.0, radius: 2.0, inward_strength: 0.5 }.apply(&mut bodies, &None, 0.01);
+        let f = force(&bodies, 0);
+        assert!(f.y > 0.0 && f.x < 0.0);
+        assert_eq!(force(&bodies, 1), Vec2::zero());
+    }
+}
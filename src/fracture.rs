@@ -0,0 +1,198 @@
+use crate::body::{Body, BodyHandle};
+use crate::math::{self, Vec2};
+use crate::random::Rng;
+use crate::shape::{self, Shape};
+
+//cells smaller than this fraction of the original are dropped, their mass goes to the others
+const MIN_FRAGMENT_FRACTION: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Breakable{
+    //normal impulse one contact manifold has to deliver in one step to break the body, summed
+    //over the manifold's points so a flat face landing hits as hard as a corner
+    pub threshold: f32,
+    //how many pieces to aim for, voronoi cells that come out too small are left out
+    pub fragments: usize,
+}
+
+impl Breakable{
+    pub fn new(threshold: f32) -> Self{
+        Self{
+            threshold,
+            fragments: 6,
+        }
+    }
+
+    pub fn with_fragments(mut self, fragments: usize) -> Self{
+        self.fragments = fragments;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FractureEvent{
+    //already removed from the world when the event is read
+    pub body: BodyHandle,
+    pub fragments: Vec<BodyHandle>,
+}
+
+//voronoi cells of the seeds cut out of a convex polygon, in the same order as the seeds
+pub fn voronoi_cells(polygon: &[Vec2], seeds: &[Vec2]) -> Vec<Vec<Vec2>>{
+    seeds.iter().enumerate().map(|(i, seed)| {
+        let mut cell = polygon.to_vec();
+        for (j, other) in seeds.iter().enumerate(){
+            if i == j || cell.len() < 3{
+                continue;
+            }
+            cell = shape::clip(&cell, (*seed + *other) * 0.5, *other - *seed);
+        }
+        cell
+    }).collect()
+}
+
+//(shape, center) of every piece, in the body's local frame
+fn circle_pieces(radius: f32, pieces: usize, rng: &mut Rng) -> Vec<(Shape, Vec2)>{
+    //a ring of equal circles that touch each other and the original outline
+    let pieces = pieces.max(2);
+    let sin = (std::f32::consts::PI / pieces as f32).sin();
+    let piece_radius = radius * sin / (1.0 + sin);
+    let start = rng.range(0.0, std::f32::consts::TAU);
+    (0..pieces).map(|i| {
+        let angle = start + i as f32 / pieces as f32 * std::f32::consts::TAU;
+        (Shape::circle(piece_radius), Vec2::new(angle.cos(), angle.sin()) * (radius - piece_radius))
+    }).collect()
+}
+
+fn polygon_pieces(vertices: &[Vec2], pieces: usize, rng: &mut Rng) -> Vec<(Shape, Vec2)>{
+    let mut min = vertices[0];
+    let mut max = vertices[0];
+    for v in vertices.iter(){
+        min = Vec2::new(min.x.min(v.x), min.y.min(v.y));
+        max = Vec2::new(max.x.max(v.x), max.y.max(v.y));
+    }
+    let outline = Shape::polygon(vertices);
+    let mut seeds = Vec::with_capacity(pieces);
+    let mut attempts = 0;
+    while seeds.len() < pieces && attempts < pieces * 100{
+        let point = Vec2::new(rng.range(min.x, max.x), rng.range(min.y, max.y));
+        if outline.contains_point(Vec2::zero(), 0.0, point){
+            seeds.push(point);
+        }
+        attempts += 1;
+    }
+
+    let min_area = outline.area() * MIN_FRAGMENT_FRACTION;
+    voronoi_cells(vertices, &seeds).into_iter()
+        .filter(|cell| cell.len() >= 3 && shape::signed_area(cell).abs() > min_area)
+        .map(|cell| (Shape::polygon(&cell), shape::polygon_centroid(&cell)))
+        .collect()
+}
+
+//the pieces a body breaks into, empty if it can't be split. mass, momentum and angular momentum
+//are kept, the pieces move as one rigid body would and take over everything else (colour, filter...)
+pub fn fracture_body(body: &Body, pieces: usize, rng: &mut Rng) -> Vec<Body>{
+    let parts = match &body.shape{
+        Shape::Circle{ radius } => circle_pieces(*radius, pieces, rng),
+        Shape::Polygon{ vertices, .. } => polygon_pieces(vertices, pieces, rng),
+    };
+    if parts.len() < 2{
+        return Vec::new();
+    }
+    let area: f32 = parts.iter().map(|(shape, _)| shape.area()).sum();
+    let density = body.mass() / area;
+
+    let mut fragments: Vec<Body> = parts.into_iter().map(|(shape, center)| {
+        let mut fragment = body.clone();
+        //pieces don't break any further
+        fragment.breakable = None;
+        fragment.set_shape(shape);
+        fragment.set_density(density);
+        fragment.position = body.position + center.rotate(body.angle);
+        fragment.force = Vec2::zero();
+        fragment.torque = 0.0;
+        fragment
+    }).collect();
+
+    let mass: f32 = fragments.iter().map(|f| f.mass()).sum();
+    let center = fragments.iter().fold(Vec2::zero(), |acc, f| acc + f.position * f.mass()) / mass;
+    let angular_momentum = body.inertia() * body.angular_velocity + (body.position - center).cross(body.velocity * body.mass());
+    let inertia: f32 = fragments.iter().map(|f| f.inertia() + f.mass() * (f.position - center).length_squared()).sum();
+    let angular_velocity = if inertia > 0.0 { angular_momentum / inertia } else { 0.0 };
+    for fragment in fragments.iter_mut(){
+        fragment.velocity = body.velocity + math::cross_sv(angular_velocity, fragment.position - center);
+        fragment.angular_velocity = angular_velocity;
+    }
+    fragments
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::world::World;
+
+    fn square(size: f32) -> Vec<Vec2>{
+        let h = size * 0.5;
+        vec![Vec2::new(-h, -h), Vec2::new(h, -h), Vec2::new(h, h), Vec2::new(-h, h)]
+    }
+
+    #[test]
+    fn voronoi_cells_tile_the_polygon(){
+        let seeds = [Vec2::new(-0.5, -0.5), Vec2::new(0.6, -0.2), Vec2::new(0.0, 0.7), Vec2::new(-0.7, 0.4)];
+        let cells = voronoi_cells(&square(2.0), &seeds);
+        assert_eq!(cells.len(), seeds.len());
+        let area: f32 = cells.iter().map(|cell| shape::signed_area(cell)).sum();
+        assert!((area - 4.0).abs() < 1e-4);
+        for (cell, seed) in cells.iter().zip(seeds.iter()){
+            assert!(Shape::polygon(cell).contains_point(shape::polygon_centroid(cell), 0.0, *seed));
+        }
+    }
+
+    #[test]
+    fn fragments_keep_mass_and_momentum(){
+        let mut rng = Rng::new(4);
+        for shape in [Shape::polygon(&square(2.0)), Shape::circle(1.0)]{
+            let mut body = Body::new(shape, Vec2::new(3.0, 1.0));
+            body.angle = 0.3;
+            body.velocity = Vec2::new(2.0, -1.0);
+            body.angular_velocity = 1.5;
+            let fragments = fracture_body(&body, 5, &mut rng);
+            assert!(fragments.len() >= 2);
+            let mass: f32 = fragments.iter().map(|f| f.mass()).sum();
+            assert!((mass - body.mass()).abs() < 1e-3 * body.mass());
+            let momentum = fragments.iter().fold(Vec2::zero(), |acc, f| acc + f.velocity * f.mass());
+            assert!((momentum - body.velocity * body.mass()).length() < 1e-3 * body.mass());
+            let origin = Vec2::zero();
+            let angular_momentum = |b: &Body| b.inertia() * b.angular_velocity + (b.position - origin).cross(b.velocity * b.mass());
+            let total: f32 = fragments.iter().map(angular_momentum).sum();
+            assert!((total - angular_momentum(&body)).abs() < 1e-2 * angular_momentum(&body).abs());
+            assert!(fragments.iter().all(|f| f.breakable.is_none()));
+        }
+    }
+
+    #[test]
+    fn hard_hits_break_bodies_into_new_handles(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        world.add_body(Body::new_static(Shape::rect(20.0, 1.0), Vec2::new(0.0, -0.5)));
+        let mut gentle = Body::new(Shape::rect(1.0, 1.0), Vec2::new(-3.0, 0.6));
+        gentle.breakable = Some(Breakable::new(5.0));
+        let gentle = world.add_body(gentle);
+        let mut falling = Body::new(Shape::rect(1.0, 1.0), Vec2::new(3.0, 3.0));
+        falling.velocity = Vec2::new(0.0, -20.0);
+        falling.breakable = Some(Breakable::new(5.0).with_fragments(4));
+        let falling = world.add_body(falling);
+        let mut events = Vec::new();
+        for _ in 0..60{
+            world.step(1.0 / 60.0);
+            events.extend_from_slice(world.fracture_events());
+        }
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.body, falling);
+        assert!(event.fragments.len() >= 2);
+        assert!(!event.fragments.contains(&event.body));
+        assert!(world.body(falling).is_none());
+        assert!(event.fragments.iter().all(|f| world.body(*f).is_some()));
+        //resting under its own weight is nowhere near the threshold
+        assert!(world.body(gentle).is_some());
+    }
+}
@@ -0,0 +1,354 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::math::Vec2;
+use crate::shape::Aabb;
+
+//event driven simulation of perfectly elastic disks in a box, collisions happen at their exact times
+//instead of being found after the fact like in World::step
+
+#[derive(Clone, Copy, Debug)]
+pub struct Disk{
+    position: Vec2,
+    pub velocity: Vec2,
+    pub radius: f32,
+    pub mass: f32,
+    //time the position belongs to, disks are only moved when something happens to them
+    time: f64,
+    //bumped on every collision, events predicted with an older count are stale
+    collisions: u32,
+}
+
+impl Disk{
+    pub fn position_at(&self, time: f64) -> Vec2{
+        self.position + self.velocity * (time - self.time) as f32
+    }
+
+    pub fn collisions(&self) -> u32{
+        self.collisions
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind{
+    Disks(usize, usize),
+    VerticalWall(usize),
+    HorizontalWall(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Event{
+    time: f64,
+    kind: EventKind,
+    counts: (u32, u32),
+}
+
+impl PartialEq for Event{
+    fn eq(&self, other: &Self) -> bool{
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Event{}
+
+impl PartialOrd for Event{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>{
+        Some(self.cmp(other))
+    }
+}
+
+//reversed so the binary heap pops the earliest event first
+impl Ord for Event{
+    fn cmp(&self, other: &Self) -> Ordering{
+        other.time.total_cmp(&self.time)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Frame{
+    pub time: f64,
+    pub positions: Vec<Vec2>,
+}
+
+pub struct HardSphereSim{
+    disks: Vec<Disk>,
+    pub bounds: Aabb,
+    time: f64,
+    queue: BinaryHeap<Event>,
+    collision_count: u64,
+    wall_collision_count: u64,
+    //momentum handed to the walls, for pressure measurements
+    wall_impulse: f64,
+}
+
+impl HardSphereSim{
+    pub fn new(bounds: Aabb) -> Self{
+        Self{
+            disks: Vec::new(),
+            bounds,
+            time: 0.0,
+            queue: BinaryHeap::new(),
+            collision_count: 0,
+            wall_collision_count: 0,
+            wall_impulse: 0.0,
+        }
+    }
+
+    pub fn add_disk(&mut self, position: Vec2, velocity: Vec2, radius: f32, mass: f32) -> usize{
+        self.disks.push(Disk{
+            position,
+            velocity,
+            radius,
+            mass,
+            time: self.time,
+            collisions: 0,
+        });
+        let index = self.disks.len() - 1;
+        self.predict(index);
+        index
+    }
+
+    pub fn disks(&self) -> &[Disk]{
+        &self.disks
+    }
+
+    pub fn time(&self) -> f64{
+        self.time
+    }
+
+    pub fn collision_count(&self) -> u64{
+        self.collision_count
+    }
+
+    pub fn wall_collision_count(&self) -> u64{
+        self.wall_collision_count
+    }
+
+    pub fn wall_impulse(&self) -> f64{
+        self.wall_impulse
+    }
+
+    pub fn positions(&self) -> Vec<Vec2>{
+        self.disks.iter().map(|d| d.position_at(self.time)).collect()
+    }
+
+    pub fn snapshot(&self) -> Frame{
+        Frame{
+            time: self.time,
+            positions: self.positions(),
+        }
+    }
+
+    pub fn kinetic_energy(&self) -> f32{
+        self.disks.iter().map(|d| 0.5 * d.mass * d.velocity.length_squared()).sum()
+    }
+
+    fn disk_collision_time(&self, i: usize, j: usize) -> Option<f64>{
+        let a = &self.disks[i];
+        let b = &self.disks[j];
+        let dr = b.position_at(self.time) - a.position_at(self.time);
+        let dv = b.velocity - a.velocity;
+        let closing = dr.dot(dv) as f64;
+        if closing >= 0.0{
+            return None;
+        }
+        let dvv = dv.length_squared() as f64;
+        let sigma = (a.radius + b.radius) as f64;
+        let drr = dr.length_squared() as f64;
+        let discriminant = closing * closing - dvv * (drr - sigma * sigma);
+        if discriminant < 0.0{
+            return None;
+        }
+        //already overlapping and still approaching, sort it out right away
+        Some((-(closing + discriminant.sqrt()) / dvv).max(0.0))
+    }
+
+    fn wall_time(position: f32, velocity: f32, radius: f32, min: f32, max: f32) -> Option<f64>{
+        if velocity > 0.0{
+            Some((((max - radius - position) / velocity) as f64).max(0.0))
+        } else if velocity < 0.0{
+            Some((((min + radius - position) / velocity) as f64).max(0.0))
+        } else{
+            None
+        }
+    }
+
+    fn push(&mut self, delay: f64, kind: EventKind){
+        let counts = match kind{
+            EventKind::Disks(i, j) => (self.disks[i].collisions, self.disks[j].collisions),
+            EventKind::VerticalWall(i) | EventKind::HorizontalWall(i) => (self.disks[i].collisions, 0),
+        };
+        self.queue.push(Event{
+            time: self.time + delay,
+            kind,
+            counts,
+        });
+    }
+
+    fn predict(&mut self, i: usize){
+        for j in 0..self.disks.len(){
+            if j == i{
+                continue;
+            }
+            if let Some(delay) = self.disk_collision_time(i, j){
+                self.push(delay, EventKind::Disks(i, j));
+            }
+        }
+        let disk = self.disks[i];
+        let position = disk.position_at(self.time);
+        if let Some(delay) = Self::wall_time(position.x, disk.velocity.x, disk.radius, self.bounds.min.x, self.bounds.max.x){
+            self.push(delay, EventKind::VerticalWall(i));
+        }
+        if let Some(delay) = Self::wall_time(position.y, disk.velocity.y, disk.radius, self.bounds.min.y, self.bounds.max.y){
+            self.push(delay, EventKind::HorizontalWall(i));
+        }
+    }
+
+    fn is_valid(&self, event: &Event) -> bool{
+        match event.kind{
+            EventKind::Disks(i, j) => self.disks[i].collisions == event.counts.0 && self.disks[j].collisions == event.counts.1,
+            EventKind::VerticalWall(i) | EventKind::HorizontalWall(i) => self.disks[i].collisions == event.counts.0,
+        }
+    }
+
+    //moves the disk's stored position up to the current time
+    fn catch_up(&mut self, i: usize){
+        let time = self.time;
+        let disk = &mut self.disks[i];
+        disk.position = disk.position_at(time);
+        disk.time = time;
+    }
+
+    fn resolve(&mut self, kind: EventKind){
+        match kind{
+            EventKind::Disks(i, j) => {
+                self.catch_up(i);
+                self.catch_up(j);
+                let (a, b) = (self.disks[i], self.disks[j]);
+                let dr = b.position - a.position;
+                let sigma = a.radius + b.radius;
+                let normal = dr / sigma;
+                let closing = dr.dot(b.velocity - a.velocity);
+                let impulse = 2.0 * a.mass * b.mass * closing / (sigma * (a.mass + b.mass));
+                self.disks[i].velocity += normal * (impulse / a.mass);
+                self.disks[j].velocity -= normal * (impulse / b.mass);
+                self.disks[i].collisions += 1;
+                self.disks[j].collisions += 1;
+                self.collision_count += 1;
+                self.predict(i);
+                self.predict(j);
+            },
+            EventKind::VerticalWall(i) | EventKind::HorizontalWall(i) => {
+                self.catch_up(i);
+                let disk = &mut self.disks[i];
+                let momentum = if let EventKind::VerticalWall(_) = kind{
+                    disk.velocity.x = -disk.velocity.x;
+                    disk.velocity.x
+                } else{
+                    disk.velocity.y = -disk.velocity.y;
+                    disk.velocity.y
+                };
+                self.wall_impulse += 2.0 * (disk.mass * momentum.abs()) as f64;
+                disk.collisions += 1;
+                self.wall_collision_count += 1;
+                self.predict(i);
+            },
+        }
+    }
+
+    //processes the next valid event, returns what happened
+    pub fn next_event(&mut self) -> Option<EventKind>{
+        while let Some(event) = self.queue.pop(){
+            if !self.is_valid(&event){
+                continue;
+            }
+            self.time = event.time;
+            self.resolve(event.kind);
+            return Some(event.kind);
+        }
+        None
+    }
+
+    //runs every event up to the given time and leaves the disks drifted to it
+    pub fn advance_to(&mut self, time: f64){
+        loop{
+            let next = loop{
+                match self.queue.peek(){
+                    Some(event) if !self.is_valid(event) => {
+                        self.queue.pop();
+                    },
+                    other => break other.map(|event| event.time),
+                }
+            };
+            match next{
+                Some(next) if next <= time => {
+                    self.next_event();
+                },
+                _ => break,
+            }
+        }
+        self.time = self.time.max(time);
+    }
+
+    //samples the simulation at a fixed interval so it can be played back by the renderer
+    pub fn record_frames(&mut self, frame_time: f64, count: usize) -> Vec<Frame>{
+        let mut frames = Vec::with_capacity(count);
+        for _ in 0..count{
+            let target = self.time + frame_time;
+            self.advance_to(target);
+            frames.push(self.snapshot());
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn unit_box() -> Aabb{
+        Aabb::new(Vec2::zero(), Vec2::new(10.0, 10.0))
+    }
+
+    #[test]
+    fn head_on_collision_happens_at_the_exact_time(){
+        let mut sim = HardSphereSim::new(unit_box());
+        let a = sim.add_disk(Vec2::new(2.0, 5.0), Vec2::new(1.0, 0.0), 0.5, 1.0);
+        let b = sim.add_disk(Vec2::new(6.0, 5.0), Vec2::new(-1.0, 0.0), 0.5, 1.0);
+        //gap of 3 closing at 2
+        assert!(matches!(sim.next_event(), Some(EventKind::Disks(i, j)) if (i.min(j), i.max(j)) == (a, b)));
+        assert!((sim.time() - 1.5).abs() < 1e-9);
+        //equal masses swap velocities
+        assert_eq!(sim.disks()[a].velocity, Vec2::new(-1.0, 0.0));
+        assert_eq!(sim.disks()[b].velocity, Vec2::new(1.0, 0.0));
+        assert_eq!(sim.next_event(), Some(EventKind::VerticalWall(a)));
+        assert!((sim.time() - 4.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn gas_keeps_its_energy_and_never_overlaps(){
+        let mut sim = HardSphereSim::new(unit_box());
+        //headings spread by the golden angle, speeds and masses varied so nothing lines up
+        for i in 0..10{
+            for j in 0..10{
+                let n = (i * 10 + j) as f32;
+                let angle = n * 2.399_963;
+                let speed = 0.2 + 0.5 * (n * 0.618_034).fract();
+                let velocity = Vec2::new(angle.cos(), angle.sin()) * speed;
+                sim.add_disk(Vec2::new(0.5 + i as f32, 0.5 + j as f32), velocity, 0.2, 1.0 + (n * 0.414_214).fract());
+            }
+        }
+        let energy = sim.kinetic_energy();
+        let frames = sim.record_frames(0.1, 500);
+        assert!((sim.kinetic_energy() - energy).abs() / energy < 1e-3);
+        assert!(sim.collision_count() > 0 && sim.wall_collision_count() > 0);
+        for frame in frames.iter(){
+            for (i, p) in frame.positions.iter().enumerate(){
+                assert!(p.x > 0.199 && p.x < 9.801 && p.y > 0.199 && p.y < 9.801);
+                for q in frame.positions[i + 1..].iter(){
+                    assert!((*p - *q).length() > 0.399, "overlap at {}", frame.time);
+                }
+            }
+        }
+    }
+}
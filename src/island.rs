@@ -0,0 +1,162 @@
+use crate::body::{Body, BodyHandle};
+use crate::collision::Manifold;
+use crate::spring::Spring;
+
+pub struct UnionFind{
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind{
+    pub fn new(size: usize) -> Self{
+        Self{
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    pub fn find(&mut self, mut x: usize) -> usize{
+        while self.parent[x] != x{
+            //path halving
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    pub fn union(&mut self, a: usize, b: usize){
+        let ra = self.find(a);
+        let rb = self.find(b);
+        if ra == rb{
+            return;
+        }
+        if self.rank[ra] < self.rank[rb]{
+            self.parent[ra] = rb;
+        } else if self.rank[ra] > self.rank[rb]{
+            self.parent[rb] = ra;
+        } else{
+            self.parent[rb] = ra;
+            self.rank[ra] += 1;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SleepSettings{
+    pub enabled: bool,
+    pub linear_tolerance: f32,
+    pub angular_tolerance: f32,
+    //seconds every body of an island has to stay slow before the island falls asleep
+    pub time_to_sleep: f32,
+}
+
+impl Default for SleepSettings{
+    fn default() -> Self{
+        Self{
+            enabled: true,
+            linear_tolerance: 0.05,
+            angular_tolerance: 2.0_f32.to_radians(),
+            time_to_sleep: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Island{
+    pub bodies: Vec<BodyHandle>,
+    pub awake: bool,
+}
+
+//groups dynamic bodies that touch through contacts or are tied by springs, static bodies never join islands
+pub fn build_islands(bodies: &[Option<Body>], manifolds: &[Manifold], springs: &[Spring]) -> Vec<Island>{
+    let mut sets = UnionFind::new(bodies.len());
+    let dynamic = |h: BodyHandle| bodies[h.0].as_ref().is_some_and(|body| body.is_dynamic());
+    //kinematic bodies stay out of islands like static ones, but anything they touch while moving is kept awake
+    let pushing = |h: BodyHandle| bodies[h.0].as_ref().is_some_and(|body| body.is_kinematic() && body.is_moving());
+    let mut pushed = vec![false; bodies.len()];
+    for manifold in manifolds{
+        if dynamic(manifold.a) && dynamic(manifold.b){
+            sets.union(manifold.a.0, manifold.b.0);
+        }
+        if pushing(manifold.a){
+            pushed[manifold.b.0] = true;
+        }
+        if pushing(manifold.b){
+            pushed[manifold.a.0] = true;
+        }
+    }
+    for spring in springs{
+        if let (Some(a), Some(b)) = (spring.a.handle(), spring.b.handle()){
+            if dynamic(a) && dynamic(b){
+                sets.union(a.0, b.0);
+            }
+        }
+    }
+
+    let mut island_of_root: Vec<Option<usize>> = vec![None; bodies.len()];
+    let mut islands: Vec<Island> = Vec::new();
+    for (i, body) in bodies.iter().enumerate(){
+        let body = match body{
+            Some(body) if body.is_dynamic() => body,
+            _ => continue,
+        };
+        let root = sets.find(i);
+        let index = match island_of_root[root]{
+            Some(index) => index,
+            None => {
+                islands.push(Island{
+                    bodies: Vec::new(),
+                    awake: false,
+                });
+                island_of_root[root] = Some(islands.len() - 1);
+                islands.len() - 1
+            },
+        };
+        islands[index].bodies.push(BodyHandle(i));
+        islands[index].awake |= body.awake || pushed[i];
+    }
+    islands
+}
+
+//an island with a single awake body wakes up completely
+pub fn wake_islands(islands: &[Island], bodies: &mut [Option<Body>]){
+    for island in islands.iter().filter(|island| island.awake){
+        for handle in &island.bodies{
+            if let Some(body) = bodies[handle.0].as_mut(){
+                if !body.awake{
+                    body.set_awake(true);
+                }
+            }
+        }
+    }
+}
+
+pub fn update_sleep(islands: &mut [Island], bodies: &mut [Option<Body>], settings: &SleepSettings, dt: f32){
+    let linear_sq = settings.linear_tolerance * settings.linear_tolerance;
+    let angular_sq = settings.angular_tolerance * settings.angular_tolerance;
+    for island in islands.iter_mut().filter(|island| island.awake){
+        let mut min_sleep_time = f32::MAX;
+        for handle in &island.bodies{
+            let body = match bodies[handle.0].as_mut(){
+                Some(body) => body,
+                None => continue,
+            };
+            if !settings.enabled || !body.can_sleep ||
+                body.velocity.length_squared() > linear_sq ||
+                body.angular_velocity * body.angular_velocity > angular_sq{
+                body.sleep_time = 0.0;
+            } else{
+                body.sleep_time += dt;
+            }
+            min_sleep_time = min_sleep_time.min(body.sleep_time);
+        }
+        if min_sleep_time >= settings.time_to_sleep{
+            for handle in &island.bodies{
+                if let Some(body) = bodies[handle.0].as_mut(){
+                    body.set_awake(false);
+                }
+            }
+            island.awake = false;
+        }
+    }
+}
@@ -11,12 +11,60 @@ mod shader;
 mod math;
 mod vertex_array;
 mod texture;
+mod debug_draw;
+mod shape;
+mod body;
+mod collision;
+mod filter;
+mod events;
+mod broadphase;
+mod solver;
+mod island;
+mod sensor;
+mod query;
+mod force;
+mod nbody;
+mod merge;
+mod periodic;
+mod electromagnetism;
+mod potential;
+mod hard_sphere;
+mod random;
+mod thermostat;
+mod analysis;
+mod diagnostics;
+mod world;
+mod container;
+mod path;
+mod fracture;
+mod spring;
+mod soft_body;
+mod rope;
+mod sph;
+mod buoyancy;
+mod material;
+mod restitution;
 
 use renderer::Renderer;
+use debug_draw::DebugDraw;
+use world::World;
+use container::Container;
+use material::MaterialTable;
+use diagnostics::{ConservationMonitor, Quantity};
+use body::Body;
+use shape::Shape;
+use math::Vec2;
+use soft_body::{SoftBody, SoftBodyBuilder};
+use rope::{Rope, RopeBuilder};
+use spring::Anchor;
+use sph::Fluid;
 
 // settings
 const SCR_WIDTH: u32 = 800;
 const SCR_HEIGHT: u32 = 600;
+const PIXELS_PER_UNIT: f32 = 50.0;
+const TIME_STEP: f32 = 1.0 / 60.0;
+const SCENE_FILE: &str = "assets/scene.txt";
 
 pub fn main() {
     // glfw: initialize and configure
@@ -62,28 +110,107 @@ pub fn main() {
         Ok(_) => (),
     }
 
+    let mut debug_draw = match DebugDraw::new(){
+        Ok(debug_draw) => debug_draw,
+        Err(err_message) => panic!("{}", err_message),
+    };
+
+    let mut world = World::new(math::Vec2::new(0.0, -9.81));
+    let (width, height) = window.get_framebuffer_size();
+    let mut container = Container::window(&mut world, width, height, PIXELS_PER_UNIT);
+    debug_draw.set_view(width, height, PIXELS_PER_UNIT);
+    //the scene still runs with default materials, but say why they aren't the ones in the file
+    let materials = MaterialTable::load(SCENE_FILE).unwrap_or_else(|err| {
+        eprintln!("{}: {}", SCENE_FILE, err);
+        MaterialTable::new()
+    });
+    world.combine_rules = materials.rules;
+    if let Some(wall) = materials.get("wall"){
+        container.apply_material(&mut world, wall);
+    }
+    let scene = build_scene(&mut world, &materials);
+    //the walls and friction take energy out, the graph shows where it goes rather than raising alarms
+    let mut monitor = ConservationMonitor::new(&mut world);
+
     while !window.should_close() {
         // events
         // -----
-        process_events(&mut window, &events);
+        process_events(&mut window, &events, &mut world, &mut container, &mut debug_draw);
+        world.step(TIME_STEP);
+        monitor.sample(&mut world, TIME_STEP);
+        window.set_title(&format!("{} bodies, {} sleeping, {} awake islands", world.body_count(), world.sleeping_body_count(), world.awake_island_count()));
         renderer.clear_surface(math::Point3::new(0.2, 0.3, 0.4));
         //renderer.draw_quad();
         //renderer.draw_texture();
         renderer.draw_texture_color();
+        for soft_body in scene.soft_bodies.iter(){
+            debug_draw.soft_body(soft_body, &world, [0.9, 0.5, 0.7, 0.6]);
+        }
+        debug_draw.world(&world);
+        for rope in scene.ropes.iter(){
+            debug_draw.rope(rope, &world, [0.6, 0.4, 0.2, 1.0]);
+        }
+        let view = debug_draw.view_size();
+        debug_draw.graph(&monitor.series(Quantity::TotalEnergy), Vec2::new(0.5, view.y - 3.5), Vec2::new(8.5, view.y - 0.5), [1.0, 0.9, 0.3, 1.0]);
+        debug_draw.flush();
         
         window.swap_buffers();
         glfw.poll_events();
     }
 }
 
+//what the scene built that the world doesn't keep track of itself
+struct Scene{
+    soft_bodies: Vec<SoftBody>,
+    ropes: Vec<Rope>,
+}
+
+//a stack that settles and falls asleep and a ball dropped on it later to wake it back up
+fn build_scene(world: &mut World, materials: &MaterialTable) -> Scene{
+    let mut add = |shape: Shape, position: Vec2, material: &str| {
+        let mut body = Body::new(shape, position);
+        if let Err(err) = materials.apply(&mut body, material){
+            eprintln!("{}: {}", SCENE_FILE, err);
+        }
+        world.add_body(body)
+    };
+    for i in 0..6{
+        add(Shape::rect(1.0, 1.0), Vec2::new(4.0, 1.5 + i as f32 * 1.05), "wood");
+    }
+    for i in 0..4{
+        add(Shape::circle(0.4), Vec2::new(8.0 + i as f32 * 0.9, 2.0), "rubber");
+    }
+    add(Shape::circle(0.5), Vec2::new(4.2, 40.0), "steel");
+    let blob = SoftBodyBuilder::ring(Vec2::new(12.0, 6.0), 1.2, 16)
+        .with_pressure(5.0)
+        .with_area_stiffness(50.0)
+        .with_color([0.9, 0.5, 0.7])
+        .build(world);
+    //a pool in the bottom right corner
+    let mut fluid = Fluid::new(0.15);
+    fluid.add_block(Vec2::new(13.5, 0.15), Vec2::new(15.85, 2.0));
+    world.add_fluid(fluid);
+    //hangs from a point near the top of the window and swings out from the side
+    let rope = RopeBuilder::new(Vec2::new(6.0, 11.0), Vec2::new(10.0, 11.0), 16)
+        .with_start(Anchor::World(Vec2::new(6.0, 11.0)))
+        .with_color([0.6, 0.4, 0.2])
+        .build(world);
+    Scene{
+        soft_bodies: vec![blob],
+        ropes: vec![rope],
+    }
+}
+
 // NOTE: not the same version as in common.rs!
-fn process_events(window: &mut glfw::Window, events: &Receiver<(f64, glfw::WindowEvent)>) {
+fn process_events(window: &mut glfw::Window, events: &Receiver<(f64, glfw::WindowEvent)>, world: &mut World, container: &mut Container, debug_draw: &mut DebugDraw) {
     for (_, event) in glfw::flush_messages(events) {
         match event {
             glfw::WindowEvent::FramebufferSize(width, height) => {
                 // make sure the viewport matches the new window dimensions; note that width and
                 // height will be significantly larger than specified on retina displays.
                 unsafe { gl::Viewport(0, 0, width, height) }
+                container.resize(world, width, height);
+                debug_draw.set_view(width, height, PIXELS_PER_UNIT);
             }
             glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => window.set_should_close(true),
             _ => {}
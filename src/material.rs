@@ -0,0 +1,330 @@
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::body::Body;
+
+//named sets of surface properties, bodies copy them in with Body::set_material. when two bodies
+//touch, the world's CombineRules decide what the contact gets from the two sides
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CombineRule{
+    Average,
+    Min,
+    Max,
+    Multiply,
+    //square root of the product, what friction always used before rules were configurable
+    GeometricMean,
+}
+
+impl CombineRule{
+    pub fn combine(self, a: f32, b: f32) -> f32{
+        match self{
+            CombineRule::Average => (a + b) * 0.5,
+            CombineRule::Min => a.min(b),
+            CombineRule::Max => a.max(b),
+            CombineRule::Multiply => a * b,
+            CombineRule::GeometricMean => (a * b).sqrt(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self>{
+        match name{
+            "average" => Some(CombineRule::Average),
+            "min" => Some(CombineRule::Min),
+            "max" => Some(CombineRule::Max),
+            "multiply" => Some(CombineRule::Multiply),
+            "geometric_mean" => Some(CombineRule::GeometricMean),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CombineRules{
+    pub restitution: CombineRule,
+    pub static_friction: CombineRule,
+    pub dynamic_friction: CombineRule,
+}
+
+impl Default for CombineRules{
+    fn default() -> Self{
+        Self{
+            restitution: CombineRule::Max,
+            static_friction: CombineRule::GeometricMean,
+            dynamic_friction: CombineRule::GeometricMean,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material{
+    pub name: String,
+    pub density: f32,
+    pub restitution: f32,
+    //has to be overcome before a resting contact starts to slide
+    pub static_friction: f32,
+    pub dynamic_friction: f32,
+    pub color: [f32; 3],
+}
+
+impl Default for Material{
+    fn default() -> Self{
+        Self{
+            name: String::from("default"),
+            density: 1.0,
+            restitution: 0.5,
+            static_friction: 0.3,
+            dynamic_friction: 0.3,
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl Material{
+    pub fn new(name: &str) -> Self{
+        Self{
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_density(mut self, density: f32) -> Self{
+        self.density = density;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self{
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, static_friction: f32, dynamic_friction: f32) -> Self{
+        self.static_friction = static_friction;
+        self.dynamic_friction = dynamic_friction;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self{
+        self.color = color;
+        self
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MaterialError{
+    #[error("Could not read the scene file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Line {line}: expected `key = value` or a [section], got `{text}`")]
+    Syntax{ line: usize, text: String },
+    #[error("Line {line}: `{value}` is not a valid value for {key}")]
+    InvalidValue{ line: usize, key: String, value: String },
+    #[error("Line {line}: unknown property `{key}`")]
+    UnknownProperty{ line: usize, key: String },
+    #[error("Line {line}: material `{name}` is defined twice")]
+    Duplicate{ line: usize, name: String },
+    #[error("No material named `{0}`")]
+    UnknownMaterial(String),
+}
+
+//which part of the scene file the parser is in
+enum Section{
+    Rules,
+    Material(usize),
+    //anything else in the scene file is none of the table's business
+    Other,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MaterialTable{
+    materials: Vec<Material>,
+    pub rules: CombineRules,
+}
+
+impl MaterialTable{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    //replaces a material with the same name
+    pub fn insert(&mut self, material: Material){
+        match self.materials.iter_mut().find(|m| m.name == material.name){
+            Some(existing) => *existing = material,
+            None => self.materials.push(material),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Material>{
+        self.materials.iter().find(|m| m.name == name)
+    }
+
+    pub fn materials(&self) -> &[Material]{
+        &self.materials
+    }
+
+    pub fn apply(&self, body: &mut Body, name: &str) -> Result<(), MaterialError>{
+        let material = self.get(name).ok_or_else(|| MaterialError::UnknownMaterial(name.to_string()))?;
+        body.set_material(material);
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MaterialError>{
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    //reads the [rules] and [material <name>] sections of a scene file, e.g.
+    //
+    //  [rules]
+    //  restitution = max
+    //  friction = average
+    //
+    //  [material rubber]
+    //  density = 1.1
+    //  restitution = 0.8
+    //  static_friction = 1.0
+    //  dynamic_friction = 0.8
+    //  color = 0.9 0.2 0.2
+    //
+    //`friction` sets both the static and the dynamic value, # starts a comment
+    pub fn parse(source: &str) -> Result<Self, MaterialError>{
+        let mut table = Self::new();
+        let mut section = Section::Other;
+        for (index, raw) in source.lines().enumerate(){
+            let line = index + 1;
+            let text = raw.split('#').next().unwrap_or("").trim();
+            if text.is_empty(){
+                continue;
+            }
+            if let Some(header) = text.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')){
+                let mut words = header.split_whitespace();
+                section = match (words.next(), words.next(), words.next()){
+                    (Some("rules"), None, _) => Section::Rules,
+                    (Some("material"), Some(name), None) => {
+                        if table.get(name).is_some(){
+                            return Err(MaterialError::Duplicate{ line, name: name.to_string() });
+                        }
+                        table.materials.push(Material::new(name));
+                        Section::Material(table.materials.len() - 1)
+                    },
+                    (Some("material"), _, _) => return Err(MaterialError::Syntax{ line, text: text.to_string() }),
+                    _ => Section::Other,
+                };
+                continue;
+            }
+            let (key, value) = match text.split_once('='){
+                Some((key, value)) => (key.trim(), value.trim()),
+                None if matches!(section, Section::Other) => continue,
+                None => return Err(MaterialError::Syntax{ line, text: text.to_string() }),
+            };
+            let invalid = || MaterialError::InvalidValue{ line, key: key.to_string(), value: value.to_string() };
+            match section{
+                Section::Rules => {
+                    let rule = CombineRule::from_name(value).ok_or_else(invalid)?;
+                    let rules = &mut table.rules;
+                    match key{
+                        "restitution" => rules.restitution = rule,
+                        "static_friction" => rules.static_friction = rule,
+                        "dynamic_friction" => rules.dynamic_friction = rule,
+                        "friction" => {
+                            rules.static_friction = rule;
+                            rules.dynamic_friction = rule;
+                        },
+                        _ => return Err(MaterialError::UnknownProperty{ line, key: key.to_string() }),
+                    }
+                },
+                Section::Material(i) => {
+                    let material = &mut table.materials[i];
+                    if key == "color"{
+                        let channels: Vec<f32> = value.split_whitespace().map(|c| c.parse::<f32>()).collect::<Result<_, _>>().map_err(|_| invalid())?;
+                        material.color = match channels[..]{
+                            [r, g, b] => [r, g, b],
+                            _ => return Err(invalid()),
+                        };
+                        continue;
+                    }
+                    let number: f32 = value.parse().map_err(|_| invalid())?;
+                    if !number.is_finite() || number < 0.0 || (key == "density" && number == 0.0){
+                        return Err(invalid());
+                    }
+                    match key{
+                        "density" => material.density = number,
+                        "restitution" => material.restitution = number,
+                        "static_friction" => material.static_friction = number,
+                        "dynamic_friction" => material.dynamic_friction = number,
+                        "friction" => {
+                            material.static_friction = number;
+                            material.dynamic_friction = number;
+                        },
+                        _ => return Err(MaterialError::UnknownProperty{ line, key: key.to_string() }),
+                    }
+                },
+                Section::Other => {},
+            }
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::math::Vec2;
+    use crate::shape::Shape;
+
+    const SCENE: &str = "
+        # the rest of the scene file is skipped
+        [bodies]
+        circle 1 2 0.5
+
+        [rules]
+        restitution = min
+        friction = average  # both of them
+
+        [material rubber]
+        density = 1.1
+        restitution = 0.8
+        static_friction = 1.0
+        dynamic_friction = 0.8
+        color = 0.9 0.2 0.2
+
+        [material ice]
+        friction = 0.05
+    ";
+
+    #[test]
+    fn a_scene_file_fills_the_table(){
+        let table = MaterialTable::parse(SCENE).unwrap();
+        assert_eq!(table.rules, CombineRules{
+            restitution: CombineRule::Min,
+            static_friction: CombineRule::Average,
+            dynamic_friction: CombineRule::Average,
+        });
+        assert_eq!(table.materials().len(), 2);
+        let rubber = Material::new("rubber").with_density(1.1).with_restitution(0.8).with_friction(1.0, 0.8).with_color([0.9, 0.2, 0.2]);
+        assert_eq!(table.get("rubber"), Some(&rubber));
+        let ice = table.get("ice").unwrap();
+        assert_eq!((ice.static_friction, ice.dynamic_friction), (0.05, 0.05));
+        assert_eq!(ice.restitution, Material::default().restitution);
+
+        let mut body = Body::new(Shape::circle(1.0), Vec2::zero());
+        table.apply(&mut body, "rubber").unwrap();
+        assert_eq!((body.restitution, body.static_friction, body.friction), (0.8, 1.0, 0.8));
+        assert!(matches!(table.apply(&mut body, "steel"), Err(MaterialError::UnknownMaterial(name)) if name == "steel"));
+    }
+
+    #[test]
+    fn mistakes_are_reported_with_their_line(){
+        let error = MaterialTable::parse("[rules]\nrestitution = loudest").unwrap_err();
+        assert!(matches!(error, MaterialError::InvalidValue{ line: 2, ref key, ref value } if key == "restitution" && value == "loudest"), "{error}");
+        let error = MaterialTable::parse("[material wood]\ndensity 0.6").unwrap_err();
+        assert!(matches!(error, MaterialError::Syntax{ line: 2, .. }), "{error}");
+        let error = MaterialTable::parse("[material wood]\n\ncolor = 0.5 0.3").unwrap_err();
+        assert!(matches!(error, MaterialError::InvalidValue{ line: 3, ref key, .. } if key == "color"), "{error}");
+        let error = MaterialTable::parse("[material wood]\ndensity = 0").unwrap_err();
+        assert!(matches!(error, MaterialError::InvalidValue{ line: 2, .. }), "{error}");
+        let error = MaterialTable::parse("[material wood]\nhardness = 2").unwrap_err();
+        assert!(matches!(error, MaterialError::UnknownProperty{ line: 2, ref key } if key == "hardness"), "{error}");
+        let error = MaterialTable::parse("[material wood]\n[material wood]").unwrap_err();
+        assert!(matches!(error, MaterialError::Duplicate{ line: 2, ref name } if name == "wood"), "{error}");
+    }
+}
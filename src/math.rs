@@ -1,3 +1,5 @@
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
 pub struct Point2{
     pub x: f32,
     pub y: f32,
@@ -32,6 +34,7 @@ impl Point3{
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vec2{
     pub x: f32,
     pub y: f32,
@@ -44,6 +47,107 @@ impl Vec2{
             y,
         }
     }
+    
+    pub fn zero() -> Self{
+        Self::new(0.0, 0.0)
+    }
+    
+    pub fn dot(self, other: Vec2) -> f32{
+        self.x * other.x + self.y * other.y
+    }
+    
+    //2d cross product, gives the z component of the 3d one
+    pub fn cross(self, other: Vec2) -> f32{
+        self.x * other.y - self.y * other.x
+    }
+    
+    pub fn length_squared(self) -> f32{
+        self.dot(self)
+    }
+    
+    pub fn length(self) -> f32{
+        self.length_squared().sqrt()
+    }
+    
+    pub fn normalize(self) -> Self{
+        let len = self.length();
+        if len > f32::EPSILON{
+            self / len
+        } else{
+            Self::zero()
+        }
+    }
+    
+    //counter-clockwise perpendicular
+    pub fn perp(self) -> Self{
+        Self::new(-self.y, self.x)
+    }
+    
+    pub fn rotate(self, angle: f32) -> Self{
+        let (sin, cos) = angle.sin_cos();
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+//cross product of a scalar (z axis) and a vector
+pub fn cross_sv(s: f32, v: Vec2) -> Vec2{
+    Vec2::new(-s * v.y, s * v.x)
+}
+
+impl Add for Vec2{
+    type Output = Vec2;
+    fn add(self, other: Vec2) -> Vec2{
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Vec2{
+    type Output = Vec2;
+    fn sub(self, other: Vec2) -> Vec2{
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f32> for Vec2{
+    type Output = Vec2;
+    fn mul(self, s: f32) -> Vec2{
+        Vec2::new(self.x * s, self.y * s)
+    }
+}
+
+impl Div<f32> for Vec2{
+    type Output = Vec2;
+    fn div(self, s: f32) -> Vec2{
+        Vec2::new(self.x / s, self.y / s)
+    }
+}
+
+impl Neg for Vec2{
+    type Output = Vec2;
+    fn neg(self) -> Vec2{
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+impl AddAssign for Vec2{
+    fn add_assign(&mut self, other: Vec2){
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl SubAssign for Vec2{
+    fn sub_assign(&mut self, other: Vec2){
+        self.x -= other.x;
+        self.y -= other.y;
+    }
+}
+
+impl MulAssign<f32> for Vec2{
+    fn mul_assign(&mut self, s: f32){
+        self.x *= s;
+        self.y *= s;
+    }
 }
 
 pub struct Vec3{
@@ -0,0 +1,152 @@
+use crate::body::{Body, BodyHandle};
+use crate::math::Vec2;
+use crate::shape::{self, Shape};
+
+//circles get this many points when they are part of a polygon merge
+const CIRCLE_HULL_POINTS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionOutcome{
+    Bounce,
+    Merge,
+}
+
+//picks the outcome for a single pair, overrides the world-wide policy
+pub type OutcomeRule = Box<dyn Fn(BodyHandle, &Body, BodyHandle, &Body) -> CollisionOutcome>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergeEvent{
+    pub survivor: BodyHandle,
+    pub absorbed: BodyHandle,
+}
+
+fn outline(body: &Body) -> Vec<Vec2>{
+    match &body.shape{
+        Shape::Circle{ radius } => (0..CIRCLE_HULL_POINTS).map(|i| {
+            let angle = i as f32 / CIRCLE_HULL_POINTS as f32 * std::f32::consts::TAU;
+            body.position + Vec2::new(angle.cos(), angle.sin()) * *radius
+        }).collect(),
+        Shape::Polygon{ .. } => body.shape.world_vertices(body.position, body.angle),
+    }
+}
+
+//one body with the mass, momentum and angular momentum of both, the rest is taken from a
+pub fn merge_bodies(a: &Body, b: &Body) -> Body{
+    let mass = a.mass() + b.mass();
+    let center = (a.position * a.mass() + b.position * b.mass()) / mass;
+    let velocity = (a.velocity * a.mass() + b.velocity * b.mass()) / mass;
+    //volume is conserved, so the density ends up somewhere between the two
+    let volume = a.mass() / a.density() + b.mass() / b.density();
+    let density = mass / volume;
+
+    let angular_momentum = |body: &Body| {
+        body.inertia() * body.angular_velocity + (body.position - center).cross(body.velocity - velocity) * body.mass()
+    };
+    let total_angular_momentum = angular_momentum(a) + angular_momentum(b);
+
+    let mut merged = a.clone();
+    merged.position = center;
+    merged.angle = 0.0;
+    merged.velocity = velocity;
+    let blend = b.mass() / mass;
+    for i in 0..3{
+        merged.color[i] = a.color[i] * (1.0 - blend) + b.color[i] * blend;
+    }
+    match (&a.shape, &b.shape){
+        (Shape::Circle{ .. }, Shape::Circle{ .. }) => {
+            merged.set_shape(Shape::circle((volume / std::f32::consts::PI).sqrt()));
+            merged.set_density(density);
+        },
+        _ => {
+            let mut points = outline(a);
+            points.extend(outline(b));
+            let hull = shape::convex_hull(&points);
+            let hull_shape = Shape::polygon(&hull);
+            //the hull covers gaps between the two, thin it out so the mass stays the same
+            let hull_density = mass / hull_shape.area();
+            merged.position = shape::polygon_centroid(&hull);
+            merged.set_shape(hull_shape);
+            merged.set_density(hull_density);
+        },
+    }
+    //the hull centroid can sit a bit off the real center of mass, move the angular momentum over to it
+    let spin = total_angular_momentum + (center - merged.position).cross(velocity * mass);
+    merged.angular_velocity = if merged.inertia() > 0.0 { spin / merged.inertia() } else { 0.0 };
+    merged.set_awake(true);
+    merged
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::world::World;
+
+    fn momentum(body: &Body) -> Vec2{
+        body.velocity * body.mass()
+    }
+
+    //about the origin
+    fn angular_momentum(body: &Body) -> f32{
+        body.inertia() * body.angular_velocity + body.position.cross(momentum(body))
+    }
+
+    fn moving(shape: Shape, position: Vec2, velocity: Vec2, spin: f32) -> Body{
+        let mut body = Body::new(shape, position);
+        body.velocity = velocity;
+        body.angular_velocity = spin;
+        body
+    }
+
+    #[test]
+    fn merged_body_keeps_mass_and_momenta(){
+        let pairs = [
+            (moving(Shape::circle(1.0), Vec2::zero(), Vec2::new(1.0, 0.0), 0.5), moving(Shape::circle(0.5), Vec2::new(1.4, 0.3), Vec2::new(-2.0, 1.0), -1.0)),
+            (moving(Shape::rect(2.0, 1.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0), 2.0), moving(Shape::circle(0.5), Vec2::new(2.3, 1.2), Vec2::new(-1.0, 0.0), 0.0)),
+        ];
+        for (a, b) in pairs{
+            let merged = merge_bodies(&a, &b);
+            assert!((merged.mass() - (a.mass() + b.mass())).abs() < 1e-4);
+            assert!((momentum(&merged) - (momentum(&a) + momentum(&b))).length() < 1e-4);
+            assert!((angular_momentum(&merged) - (angular_momentum(&a) + angular_momentum(&b))).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn merged_circles_keep_their_area(){
+        let merged = merge_bodies(&Body::new(Shape::circle(1.0), Vec2::zero()), &Body::new(Shape::circle(0.5), Vec2::new(1.5, 0.0)));
+        match merged.shape{
+            Shape::Circle{ radius } => assert!((radius - 1.25f32.sqrt()).abs() < 1e-5),
+            Shape::Polygon{ .. } => panic!("two circles should merge into a circle"),
+        }
+    }
+
+    #[test]
+    fn heavier_body_absorbs_the_lighter_one(){
+        let mut world = World::new(Vec2::zero());
+        world.collision_outcome = CollisionOutcome::Merge;
+        let big = world.add_body(moving(Shape::circle(1.0), Vec2::zero(), Vec2::new(1.0, 0.0), 0.0));
+        let small = world.add_body(moving(Shape::circle(0.5), Vec2::new(3.0, 0.2), Vec2::new(-2.0, 0.0), 0.0));
+        let total = momentum(world.body(big).unwrap()) + momentum(world.body(small).unwrap());
+        let mut events = Vec::new();
+        for _ in 0..120{
+            world.step(1.0 / 60.0);
+            events.extend_from_slice(world.merge_events());
+        }
+        assert_eq!(events, vec![MergeEvent{ survivor: big, absorbed: small }]);
+        assert_eq!(world.body_count(), 1);
+        assert!((momentum(world.body(big).unwrap()) - total).length() < 1e-3);
+    }
+
+    #[test]
+    fn outcome_rule_overrides_the_policy(){
+        let mut world = World::new(Vec2::zero());
+        world.collision_outcome = CollisionOutcome::Merge;
+        world.set_outcome_rule(Box::new(|_, a, _, b| if a.shape.area() > 1.0 && b.shape.area() > 1.0 { CollisionOutcome::Bounce } else { CollisionOutcome::Merge }));
+        world.add_body(moving(Shape::circle(1.0), Vec2::zero(), Vec2::new(1.0, 0.0), 0.0));
+        world.add_body(moving(Shape::circle(1.0), Vec2::new(3.0, 0.0), Vec2::new(-1.0, 0.0), 0.0));
+        for _ in 0..120{
+            world.step(1.0 / 60.0);
+        }
+        assert_eq!(world.body_count(), 2);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::body::Body;
+use crate::force::ForceGenerator;
+use crate::math::Vec2;
+use crate::periodic::{self, PeriodicBounds};
+
+//beyond this depth points are treated as one clump, stops the tree from splitting forever on equal positions
+const MAX_TREE_DEPTH: usize = 32;
+
+//mutual gravity between all dynamic bodies
+pub struct NBodyGravity{
+    pub gravitational_constant: f32,
+    //keeps the force finite when two bodies get very close
+    pub softening: f32,
+    //barnes-hut opening angle, 0 is exact, bigger is faster and rougher
+    pub theta: f32,
+    //with this many bodies or fewer the exact sum is used
+    pub direct_threshold: usize,
+}
+
+impl NBodyGravity{
+    pub fn new(gravitational_constant: f32) -> Self{
+        Self{
+            gravitational_constant,
+            softening: 0.05,
+            theta: 0.5,
+            direct_threshold: 64,
+        }
+    }
+
+    pub fn accelerations(&self, positions: &[Vec2], masses: &[f32], periodic: &Option<PeriodicBounds>) -> Vec<Vec2>{
+        if positions.len() <= self.direct_threshold{
+            direct_accelerations(positions, masses, self.gravitational_constant, self.softening, periodic)
+        } else{
+            barnes_hut_accelerations(positions, masses, self.gravitational_constant, self.softening, self.theta, periodic)
+        }
+    }
+}
+
+impl ForceGenerator for NBodyGravity{
+    fn apply(&mut self, bodies: &mut [Option<Body>], periodic: &Option<PeriodicBounds>, _dt: f32){
+        let mut indices = Vec::new();
+        let mut positions = Vec::new();
+        let mut masses = Vec::new();
+        //sleeping bodies still pull on the others
+        for (i, body) in bodies.iter().enumerate(){
+            if let Some(body) = body{
+                if body.is_dynamic() && body.mass() > 0.0{
+                    indices.push(i);
+                    positions.push(body.position);
+                    masses.push(body.mass());
+                }
+            }
+        }
+        let accelerations = self.accelerations(&positions, &masses, periodic);
+        for (i, acceleration) in indices.into_iter().zip(accelerations){
+            let body = bodies[i].as_mut().unwrap();
+            if body.is_awake(){
+                body.force += acceleration * body.mass();
+            }
+        }
+    }
+
+    //always the exact pair sum, this is only for diagnostics
+    fn potential_energy(&mut self, bodies: &[Option<Body>], periodic: &Option<PeriodicBounds>) -> f32{
+        let sources: Vec<&Body> = bodies.iter().flatten().filter(|body| body.is_dynamic() && body.mass() > 0.0).collect();
+        let mut energy = 0.0;
+        for (n, a) in sources.iter().enumerate(){
+            for b in sources[(n + 1)..].iter(){
+                let dist_sq = periodic::separation(periodic, a.position, b.position).length_squared() + self.softening * self.softening;
+                if dist_sq > 0.0{
+                    energy -= self.gravitational_constant * a.mass() * b.mass() / dist_sq.sqrt();
+                }
+            }
+        }
+        energy
+    }
+}
+
+fn softened_pull(offset: Vec2, mass: f32, gravitational_constant: f32, softening: f32) -> Vec2{
+    let dist_sq = offset.length_squared() + softening * softening;
+    if dist_sq <= 0.0{
+        return Vec2::zero();
+    }
+    offset * (gravitational_constant * mass / (dist_sq * dist_sq.sqrt()))
+}
+
+//exact O(n^2) sum, each pair pulls through its nearest image when periodic
+pub fn direct_accelerations(positions: &[Vec2], masses: &[f32], gravitational_constant: f32, softening: f32, periodic: &Option<PeriodicBounds>) -> Vec<Vec2>{
+    let mut accelerations = vec![Vec2::zero(); positions.len()];
+    for i in 0..positions.len(){
+        for j in (i + 1)..positions.len(){
+            let offset = periodic::separation(periodic, positions[i], positions[j]);
+            accelerations[i] += softened_pull(offset, masses[j], gravitational_constant, softening);
+            accelerations[j] -= softened_pull(offset, masses[i], gravitational_constant, softening);
+        }
+    }
+    accelerations
+}
+
+pub fn barnes_hut_accelerations(positions: &[Vec2], masses: &[f32], gravitational_constant: f32, softening: f32, theta: f32, periodic: &Option<PeriodicBounds>) -> Vec<Vec2>{
+    let tree = QuadTree::build(positions, masses);
+    let pull = |offset: Vec2, mass: f32| softened_pull(offset, mass, gravitational_constant, softening);
+    (0..positions.len())
+        .map(|i| tree.acceleration(i, positions, masses, theta, periodic, pull))
+        .collect()
+}
+
+struct Node{
+    center: Vec2,
+    half_size: f32,
+    mass: f32,
+    center_of_mass: Vec2,
+    children: Option<[usize; 4]>,
+    bodies: Vec<usize>,
+}
+
+impl Node{
+    fn new(center: Vec2, half_size: f32) -> Self{
+        Self{
+            center,
+            half_size,
+            mass: 0.0,
+            center_of_mass: Vec2::zero(),
+            children: None,
+            bodies: Vec::new(),
+        }
+    }
+
+    fn quadrant(&self, point: Vec2) -> usize{
+        (if point.x >= self.center.x { 1 } else { 0 }) + (if point.y >= self.center.y { 2 } else { 0 })
+    }
+}
+
+pub struct QuadTree{
+    nodes: Vec<Node>,
+}
+
+impl QuadTree{
+    pub fn build(positions: &[Vec2], masses: &[f32]) -> Self{
+        let mut min = Vec2::new(f32::MAX, f32::MAX);
+        let mut max = Vec2::new(f32::MIN, f32::MIN);
+        for p in positions{
+            min = Vec2::new(min.x.min(p.x), min.y.min(p.y));
+            max = Vec2::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        let half_size = ((max.x - min.x).max(max.y - min.y) * 0.5).max(f32::EPSILON) * 1.01;
+        let mut tree = Self{
+            nodes: vec![Node::new((min + max) * 0.5, half_size)],
+        };
+        if positions.is_empty(){
+            return tree;
+        }
+        for i in 0..positions.len(){
+            tree.insert(0, i, positions, 0);
+        }
+        tree.summarize(0, positions, masses);
+        tree
+    }
+
+    fn insert(&mut self, node: usize, body: usize, positions: &[Vec2], depth: usize){
+        if let Some(children) = self.nodes[node].children{
+            let quadrant = self.nodes[node].quadrant(positions[body]);
+            self.insert(children[quadrant], body, positions, depth + 1);
+            return;
+        }
+        if self.nodes[node].bodies.is_empty() || depth >= MAX_TREE_DEPTH{
+            self.nodes[node].bodies.push(body);
+            return;
+        }
+        //split the leaf and push both bodies down
+        let center = self.nodes[node].center;
+        let quarter = self.nodes[node].half_size * 0.5;
+        let mut children = [0; 4];
+        for (q, child) in children.iter_mut().enumerate(){
+            let offset = Vec2::new(if q & 1 == 1 { quarter } else { -quarter }, if q & 2 == 2 { quarter } else { -quarter });
+            self.nodes.push(Node::new(center + offset, quarter));
+            *child = self.nodes.len() - 1;
+        }
+        self.nodes[node].children = Some(children);
+        let existing = std::mem::take(&mut self.nodes[node].bodies);
+        for b in existing.into_iter().chain(std::iter::once(body)){
+            let quadrant = self.nodes[node].quadrant(positions[b]);
+            self.insert(children[quadrant], b, positions, depth + 1);
+        }
+    }
+
+    fn summarize(&mut self, node: usize, positions: &[Vec2], masses: &[f32]){
+        let mut mass = 0.0;
+        let mut weighted = Vec2::zero();
+        if let Some(children) = self.nodes[node].children{
+            for child in children{
+                self.summarize(child, positions, masses);
+                mass += self.nodes[child].mass;
+                weighted += self.nodes[child].center_of_mass * self.nodes[child].mass;
+            }
+        } else{
+            for &b in self.nodes[node].bodies.iter(){
+                mass += masses[b];
+                weighted += positions[b] * masses[b];
+            }
+        }
+        self.nodes[node].mass = mass;
+        self.nodes[node].center_of_mass = if mass > 0.0 { weighted / mass } else { self.nodes[node].center };
+    }
+
+    fn acceleration(&self, body: usize, positions: &[Vec2], masses: &[f32], theta: f32, periodic: &Option<PeriodicBounds>, pull: impl Fn(Vec2, f32) -> Vec2) -> Vec2{
+        let mut acceleration = Vec2::zero();
+        let position = positions[body];
+        //a cell reaching past half the box has bodies whose nearest images are on different sides, those are always opened
+        let half_box = match periodic{
+            Some(periodic) => periodic.size() * 0.5,
+            None => Vec2::new(f32::INFINITY, f32::INFINITY),
+        };
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop(){
+            let node = &self.nodes[node];
+            if node.mass <= 0.0{
+                continue;
+            }
+            match node.children{
+                None => {
+                    for &other in node.bodies.iter().filter(|other| **other != body){
+                        acceleration += pull(periodic::separation(periodic, position, positions[other]), masses[other]);
+                    }
+                },
+                Some(children) => {
+                    let offset = periodic::separation(periodic, position, node.center_of_mass);
+                    let distance = offset.length();
+                    //far enough away to count the whole cell as a single mass
+                    let to_center = periodic::separation(periodic, node.center, position);
+                    let inside = to_center.x.abs() <= node.half_size && to_center.y.abs() <= node.half_size;
+                    let one_image = offset.x.abs() + node.half_size * 2.0 < half_box.x && offset.y.abs() + node.half_size * 2.0 < half_box.y;
+                    if !inside && one_image && node.half_size * 2.0 < theta * distance{
+                        acceleration += pull(offset, node.mass);
+                    } else{
+                        stack.extend_from_slice(&children);
+                    }
+                },
+            }
+        }
+        acceleration
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::shape::Aabb;
+
+    const G: f32 = 1.0;
+    const SOFTENING: f32 = 0.1;
+
+    //scattered but repeatable: a small xorshift in [0, 1)
+    fn cloud(count: usize) -> (Vec<Vec2>, Vec<f32>){
+        let mut state = 12345u32;
+        let mut next = move ||{
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 8) as f32 / (1u32 << 24) as f32
+        };
+        let mut positions = Vec::with_capacity(count);
+        let mut masses = Vec::with_capacity(count);
+        for _ in 0..count{
+            positions.push(Vec2::new(next() * 100.0 - 50.0, next() * 100.0 - 50.0));
+            masses.push(next() + 0.5);
+        }
+        (positions, masses)
+    }
+
+    //worst error over the cloud, relative to each body's own acceleration
+    fn worst_relative_error(direct: &[Vec2], approximate: &[Vec2]) -> f32{
+        direct.iter().zip(approximate).map(|(d, a)| (*d - *a).length() / d.length()).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn barnes_hut_with_zero_theta_is_the_direct_sum(){
+        let (positions, masses) = cloud(500);
+        let direct = direct_accelerations(&positions, &masses, G, SOFTENING, &None);
+        let tree = barnes_hut_accelerations(&positions, &masses, G, SOFTENING, 0.0, &None);
+        //only the order of the additions differs
+        let error = worst_relative_error(&direct, &tree);
+        assert!(error < 1e-4, "{}", error);
+    }
+
+    #[test]
+    fn barnes_hut_with_half_theta_is_close(){
+        let (positions, masses) = cloud(500);
+        let direct = direct_accelerations(&positions, &masses, G, SOFTENING, &None);
+        let tree = barnes_hut_accelerations(&positions, &masses, G, SOFTENING, 0.5, &None);
+        //bodies in the middle of the cloud are pulled every way and end up with almost nothing, so their own
+        //relative error says little. on average the error is within 2%, and no body is off by more than 5%
+        //of a typical acceleration
+        let mean_error = direct.iter().zip(&tree).map(|(d, a)| (*d - *a).length() / d.length()).sum::<f32>() / direct.len() as f32;
+        let typical = direct.iter().map(|d| d.length()).sum::<f32>() / direct.len() as f32;
+        let worst = direct.iter().zip(&tree).map(|(d, a)| (*d - *a).length()).fold(0.0, f32::max) / typical;
+        assert!(mean_error < 0.02, "{}", mean_error);
+        assert!(worst < 0.05, "{}", worst);
+    }
+
+    #[test]
+    fn softening_keeps_close_pairs_finite(){
+        let positions = [Vec2::new(1.0, 1.0), Vec2::new(1.0 + 1e-6, 1.0), Vec2::new(1.0, 1.0)];
+        let masses = [1.0; 3];
+        //the softened pull never gets stronger than this, whatever the distance
+        let limit = 2.0 / (3.0 * 3.0f32.sqrt()) * G * 2.0 / (SOFTENING * SOFTENING);
+        for accelerations in [direct_accelerations(&positions, &masses, G, SOFTENING, &None), barnes_hut_accelerations(&positions, &masses, G, SOFTENING, 0.5, &None)]{
+            for a in accelerations{
+                assert!(a.x.is_finite() && a.y.is_finite());
+                assert!(a.length() <= limit);
+            }
+        }
+    }
+
+    #[test]
+    fn periodic_pairs_pull_through_the_nearest_image(){
+        let (positions, masses) = cloud(300);
+        let bounds = PeriodicBounds::new(Aabb::new(Vec2::zero(), Vec2::new(100.0, 100.0)));
+        let positions: Vec<Vec2> = positions.iter().map(|p| bounds.wrap(*p)).collect();
+        let periodic = Some(bounds);
+        let direct = direct_accelerations(&positions, &masses, G, SOFTENING, &periodic);
+        //a pair 2 apart across the edge
+        let pair = direct_accelerations(&[Vec2::new(1.0, 50.0), Vec2::new(99.0, 50.0)], &[1.0, 1.0], G, SOFTENING, &periodic);
+        assert!(pair[0].x < 0.0 && pair[1].x > 0.0);
+        assert!((pair[0].length() - softened_pull(Vec2::new(2.0, 0.0), 1.0, G, SOFTENING).length()).abs() < 1e-6);
+        let tree = barnes_hut_accelerations(&positions, &masses, G, SOFTENING, 0.0, &periodic);
+        assert!(worst_relative_error(&direct, &tree) < 1e-4);
+        //the cloud fills the whole box, so the pulls mostly cancel and a typical acceleration is small
+        let tree = barnes_hut_accelerations(&positions, &masses, G, SOFTENING, 0.5, &periodic);
+        let mean_error = direct.iter().zip(&tree).map(|(d, a)| (*d - *a).length() / d.length()).sum::<f32>() / direct.len() as f32;
+        let typical = direct.iter().map(|d| d.length()).sum::<f32>() / direct.len() as f32;
+        let worst = direct.iter().zip(&tree).map(|(d, a)| (*d - *a).length()).fold(0.0, f32::max) / typical;
+        assert!(mean_error < 0.02, "{}", mean_error);
+        assert!(worst < 0.1, "{}", worst);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::body::BodyHandle;
+use crate::math::Vec2;
+
+//scripted motion for kinematic bodies. the follower doesn't teleport the body, it sets the velocity
+//that gets it to the next point on the path in one step, so contacts see how fast it really moves
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathMode{
+    //stops at the end
+    Once,
+    //jumps back to the start
+    Loop,
+    //runs back and forth
+    PingPong,
+}
+
+impl PathMode{
+    fn local_time(self, time: f32, duration: f32) -> f32{
+        if duration <= 0.0{
+            return 0.0;
+        }
+        match self{
+            PathMode::Once => time.clamp(0.0, duration),
+            PathMode::Loop => time.rem_euclid(duration),
+            PathMode::PingPong => {
+                let t = time.rem_euclid(2.0 * duration);
+                if t > duration { 2.0 * duration - t } else { t }
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe{
+    pub time: f32,
+    pub position: Vec2,
+    pub angle: f32,
+}
+
+impl Keyframe{
+    pub fn new(time: f32, position: Vec2, angle: f32) -> Self{
+        Self{
+            time,
+            position,
+            angle,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Path{
+    //straight line between two points at constant speed, with PingPong it's a piston
+    Linear{ from: Vec2, to: Vec2, duration: f32, mode: PathMode },
+    //orbits the center, angular_speed in radians per second
+    Circular{ center: Vec2, radius: f32, angular_speed: f32, phase: f32 },
+    //catmull-rom spline through the keyframe positions, angles are interpolated linearly.
+    //keyframes must be sorted by time
+    Spline{ keyframes: Vec<Keyframe>, mode: PathMode },
+}
+
+impl Path{
+    pub fn position_at(&self, time: f32) -> Vec2{
+        match self{
+            Path::Linear{ from, to, duration, mode } => {
+                let u = if *duration > 0.0 { mode.local_time(time, *duration) / duration } else { 1.0 };
+                *from + (*to - *from) * u
+            },
+            Path::Circular{ center, radius, angular_speed, phase } => {
+                let angle = phase + angular_speed * time;
+                *center + Vec2::new(angle.cos(), angle.sin()) * *radius
+            },
+            Path::Spline{ keyframes, mode } => {
+                let (i, u) = match spline_segment(keyframes, *mode, time){
+                    Some(segment) => segment,
+                    None => return keyframes.first().map_or(Vec2::zero(), |k| k.position),
+                };
+                let at = |index: isize| keyframes[index.clamp(0, keyframes.len() as isize - 1) as usize].position;
+                let i = i as isize;
+                catmull_rom(at(i - 1), at(i), at(i + 1), at(i + 2), u)
+            },
+        }
+    }
+
+    //None when the path doesn't say anything about rotation
+    pub fn angle_at(&self, time: f32) -> Option<f32>{
+        match self{
+            Path::Spline{ keyframes, mode } => {
+                let (i, u) = match spline_segment(keyframes, *mode, time){
+                    Some(segment) => segment,
+                    None => return keyframes.first().map(|k| k.angle),
+                };
+                Some(keyframes[i].angle + (keyframes[i + 1].angle - keyframes[i].angle) * u)
+            },
+            _ => None,
+        }
+    }
+}
+
+//index of the keyframe starting the segment and how far along it the time is
+fn spline_segment(keyframes: &[Keyframe], mode: PathMode, time: f32) -> Option<(usize, f32)>{
+    if keyframes.len() < 2{
+        return None;
+    }
+    let start = keyframes[0].time;
+    let duration = keyframes[keyframes.len() - 1].time - start;
+    let time = start + mode.local_time(time - start, duration);
+    let i = keyframes.windows(2).position(|pair| time <= pair[1].time).unwrap_or(keyframes.len() - 2);
+    let span = keyframes[i + 1].time - keyframes[i].time;
+    let u = if span > 0.0 { ((time - keyframes[i].time) / span).clamp(0.0, 1.0) } else { 1.0 };
+    Some((i, u))
+}
+
+pub fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, u: f32) -> Vec2{
+    let u2 = u * u;
+    let u3 = u2 * u;
+    (p1 * 2.0
+        + (p2 - p0) * u
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * u2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * u3) * 0.5
+}
+
+pub struct PathFollower{
+    //should be kinematic, dynamic bodies would still be knocked around by contacts
+    pub body: BodyHandle,
+    pub path: Path,
+    pub time: f32,
+    //constant spin for paths without angles, a stirrer is a path that stays put plus spin
+    pub angular_velocity: f32,
+}
+
+impl PathFollower{
+    pub fn new(body: BodyHandle, path: Path) -> Self{
+        Self{
+            body,
+            path,
+            time: 0.0,
+            angular_velocity: 0.0,
+        }
+    }
+
+    pub fn with_spin(mut self, angular_velocity: f32) -> Self{
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    //advances the path by dt and returns the velocities that take the body from where it is now to the new point
+    pub fn advance(&mut self, position: Vec2, angle: f32, dt: f32) -> (Vec2, f32){
+        self.time += dt;
+        let velocity = (self.path.position_at(self.time) - position) / dt;
+        let angular_velocity = match self.path.angle_at(self.time){
+            Some(target) => (target - angle) / dt,
+            None => self.angular_velocity,
+        };
+        (velocity, angular_velocity)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::body::Body;
+    use crate::shape::Shape;
+    use crate::world::World;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn close(a: Vec2, b: Vec2) -> bool{
+        (a - b).length() < 1e-4
+    }
+
+    #[test]
+    fn linear_paths_follow_their_mode(){
+        let line = |mode| Path::Linear{ from: Vec2::zero(), to: Vec2::new(4.0, 0.0), duration: 2.0, mode };
+        assert!(close(line(PathMode::Once).position_at(1.0), Vec2::new(2.0, 0.0)));
+        assert!(close(line(PathMode::Once).position_at(5.0), Vec2::new(4.0, 0.0)));
+        assert!(close(line(PathMode::Loop).position_at(2.5), Vec2::new(1.0, 0.0)));
+        assert!(close(line(PathMode::PingPong).position_at(2.5), Vec2::new(3.0, 0.0)));
+        assert!(close(line(PathMode::PingPong).position_at(4.0), Vec2::zero()));
+    }
+
+    #[test]
+    fn splines_pass_through_their_keyframes(){
+        let keyframes = vec![
+            Keyframe::new(0.0, Vec2::zero(), 0.0),
+            Keyframe::new(1.0, Vec2::new(2.0, 1.0), 1.0),
+            Keyframe::new(3.0, Vec2::new(4.0, -1.0), 2.0),
+        ];
+        let path = Path::Spline{ keyframes: keyframes.clone(), mode: PathMode::Once };
+        for keyframe in keyframes.iter(){
+            assert!(close(path.position_at(keyframe.time), keyframe.position));
+            assert!((path.angle_at(keyframe.time).unwrap() - keyframe.angle).abs() < 1e-5);
+        }
+        assert!((path.angle_at(2.0).unwrap() - 1.5).abs() < 1e-5);
+        let circle = Path::Circular{ center: Vec2::new(1.0, 1.0), radius: 2.0, angular_speed: 1.0, phase: 0.0 };
+        assert!(close(circle.position_at(std::f32::consts::FRAC_PI_2), Vec2::new(1.0, 3.0)));
+        assert_eq!(circle.angle_at(1.0), None);
+    }
+
+    #[test]
+    fn followers_snap_their_body_onto_the_path(){
+        let mut world = World::new(Vec2::zero());
+        let body = world.add_body(Body::new_kinematic(Shape::rect(2.0, 0.5), Vec2::new(50.0, 50.0)));
+        let keyframes = vec![Keyframe::new(0.0, Vec2::new(1.0, 1.0), 0.5), Keyframe::new(2.0, Vec2::new(5.0, 1.0), 0.5)];
+        world.add_path_follower(PathFollower::new(body, Path::Spline{ keyframes, mode: PathMode::Once }));
+        assert!(close(world.body(body).unwrap().position, Vec2::new(1.0, 1.0)));
+        assert_eq!(world.body(body).unwrap().angle, 0.5);
+        world.step(DT);
+        //moving along the path, not flying in from where it was put
+        assert!(world.body(body).unwrap().velocity.length() < 5.0);
+        for _ in 0..119{
+            world.step(DT);
+        }
+        assert!((world.body(body).unwrap().position - Vec2::new(5.0, 1.0)).length() < 0.01);
+    }
+
+    #[test]
+    fn a_moving_platform_carries_what_rests_on_it(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        let platform = world.add_body(Body::new_kinematic(Shape::rect(4.0, 0.5), Vec2::zero()));
+        world.add_path_follower(PathFollower::new(platform, Path::Linear{ from: Vec2::zero(), to: Vec2::new(0.0, 3.0), duration: 2.0, mode: PathMode::Once }));
+        let crate_body = world.add_body(Body::new(Shape::rect(1.0, 1.0), Vec2::new(0.0, 0.76)));
+        for _ in 0..120{
+            world.step(DT);
+        }
+        let y = world.body(crate_body).unwrap().position.y;
+        assert!(y > 3.5 && y < 3.9, "{y}");
+    }
+}
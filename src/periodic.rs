@@ -0,0 +1,204 @@
+use crate::math::Vec2;
+use crate::shape::Aabb;
+
+//wrap-around world, whatever leaves through one edge comes back in through the opposite one.
+//distances use the minimum image, the closest of all the periodic copies, so bodies must stay
+//smaller than half the box or they would touch themselves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PeriodicBounds{
+    pub bounds: Aabb,
+}
+
+impl PeriodicBounds{
+    pub fn new(bounds: Aabb) -> Self{
+        Self{
+            bounds,
+        }
+    }
+
+    pub fn size(&self) -> Vec2{
+        self.bounds.max - self.bounds.min
+    }
+
+    pub fn area(&self) -> f32{
+        let size = self.size();
+        size.x * size.y
+    }
+
+    pub fn wrap(&self, point: Vec2) -> Vec2{
+        let size = self.size();
+        let local = point - self.bounds.min;
+        self.bounds.min + Vec2::new(local.x.rem_euclid(size.x), local.y.rem_euclid(size.y))
+    }
+
+    //shortest offset between two images, each component ends up within half the box
+    pub fn minimum_image(&self, offset: Vec2) -> Vec2{
+        let size = self.size();
+        Vec2::new(
+            offset.x - size.x * (offset.x / size.x).round(),
+            offset.y - size.y * (offset.y / size.y).round(),
+        )
+    }
+
+    //shifts where copies of something with this aabb have to go so the parts hanging over
+    //an edge also show up on the other side, empty when it is fully inside
+    pub fn image_offsets(&self, aabb: &Aabb) -> Vec<Vec2>{
+        let size = self.size();
+        let shift = |min: f32, max: f32, low: f32, high: f32, size: f32| {
+            if min < low{
+                Some(size)
+            } else if max > high{
+                Some(-size)
+            } else{
+                None
+            }
+        };
+        let x = shift(aabb.min.x, aabb.max.x, self.bounds.min.x, self.bounds.max.x, size.x);
+        let y = shift(aabb.min.y, aabb.max.y, self.bounds.min.y, self.bounds.max.y, size.y);
+        let mut offsets = Vec::new();
+        if let Some(x) = x{
+            offsets.push(Vec2::new(x, 0.0));
+        }
+        if let Some(y) = y{
+            offsets.push(Vec2::new(0.0, y));
+        }
+        if let (Some(x), Some(y)) = (x, y){
+            offsets.push(Vec2::new(x, y));
+        }
+        offsets
+    }
+}
+
+//offset from `from` to `to`, going across the edges when that is shorter
+pub fn separation(periodic: &Option<PeriodicBounds>, from: Vec2, to: Vec2) -> Vec2{
+    match periodic{
+        Some(periodic) => periodic.minimum_image(to - from),
+        None => to - from,
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::body::{Body, BodyHandle};
+    use crate::electromagnetism::Coulomb;
+    use crate::force::ForceGenerator;
+    use crate::nbody::NBodyGravity;
+    use crate::potential::{Harmonic, PairForce};
+    use crate::shape::Shape;
+    use crate::world::World;
+
+    fn bounds() -> PeriodicBounds{
+        PeriodicBounds::new(Aabb::new(Vec2::zero(), Vec2::new(10.0, 10.0)))
+    }
+
+    fn world() -> World{
+        let mut world = World::new(Vec2::zero());
+        world.periodic = Some(bounds());
+        world.sleep.enabled = false;
+        world
+    }
+
+    #[test]
+    fn wrap_and_minimum_image_stay_in_the_box(){
+        let periodic = bounds();
+        assert_eq!(periodic.wrap(Vec2::new(-0.5, 10.5)), Vec2::new(9.5, 0.5));
+        assert!((periodic.minimum_image(Vec2::new(9.0, -9.0)) - Vec2::new(-1.0, 1.0)).length() < 1e-5);
+        assert_eq!(periodic.image_offsets(&Aabb::new(Vec2::new(4.0, 4.0), Vec2::new(5.0, 5.0))), Vec::new());
+        //over the left and the top edge, so the copies go right, down and to the corner
+        let offsets = periodic.image_offsets(&Aabb::new(Vec2::new(-0.5, 9.0), Vec2::new(0.5, 10.5)));
+        assert_eq!(offsets, vec![Vec2::new(10.0, 0.0), Vec2::new(0.0, -10.0), Vec2::new(10.0, -10.0)]);
+    }
+
+    #[test]
+    fn bodies_collide_across_an_edge(){
+        let mut world = world();
+        let mut a = Body::new(Shape::circle(0.5), Vec2::new(0.8, 5.0));
+        a.velocity = Vec2::new(-2.0, 0.0);
+        let mut b = Body::new(Shape::circle(0.5), Vec2::new(9.2, 5.0));
+        b.velocity = Vec2::new(2.0, 0.0);
+        let (a, b) = (world.add_body(a), world.add_body(b));
+        let mut touched = false;
+        for _ in 0..30{
+            world.step(1.0 / 60.0);
+            touched |= world.is_touching(a, b);
+        }
+        assert!(touched);
+        assert!(world.body(a).unwrap().velocity.x > 0.0);
+        assert!(world.body(b).unwrap().velocity.x < 0.0);
+    }
+
+    #[test]
+    fn bodies_over_an_edge_have_ghosts_and_are_found_once(){
+        let mut world = world();
+        let inside = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(5.0, 5.0)));
+        let corner = world.add_body(Body::new(Shape::rect(1.0, 1.0), Vec2::new(9.8, 0.2)));
+        world.step(1.0 / 60.0);
+        let ghosts = world.ghost_positions();
+        assert_eq!(ghosts.len(), 3);
+        assert!(ghosts.iter().all(|(handle, _)| *handle == corner));
+        assert!(ghosts.iter().any(|(_, position)| (*position - Vec2::new(-0.2, 10.2)).length() < 0.05));
+        //the query box covers the body and its image across the x edge
+        let found = world.query_aabb(&Aabb::new(Vec2::new(-1.0, 0.0), Vec2::new(10.0, 1.0)), |_, _| true);
+        assert_eq!(found, vec![corner]);
+        assert!(!found.contains(&inside));
+    }
+
+    #[test]
+    fn queries_find_bodies_through_the_edges(){
+        let mut world = world();
+        //hangs over the right edge
+        let over = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(9.8, 5.0)));
+        //fully inside, next to the left edge
+        let near = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(0.6, 2.0)));
+        world.step(1.0 / 60.0);
+        let anything = |_: BodyHandle, _: &Body| true;
+        assert_eq!(world.query_point(Vec2::new(0.1, 5.0), anything), vec![over]);
+        assert_eq!(world.query_point(Vec2::new(10.3, 2.0), anything), vec![near]);
+        assert_eq!(world.query_shape(&Shape::circle(0.2), Vec2::new(0.4, 5.0), 0.0, anything), vec![over]);
+        assert_eq!(world.query_aabb(&Aabb::new(Vec2::new(0.0, 4.9), Vec2::new(0.1, 5.1)), anything), vec![over]);
+        let hit = world.raycast(Vec2::new(2.0, 5.0), Vec2::new(-0.5, 5.0), anything).unwrap();
+        assert_eq!(hit.body, over);
+        assert!((hit.point - Vec2::new(0.3, 5.0)).length() < 1e-3);
+        assert!((hit.normal - Vec2::new(1.0, 0.0)).length() < 1e-3);
+        assert_eq!(world.raycast_all(Vec2::new(2.0, 5.0), Vec2::new(-3.0, 5.0), anything).len(), 1);
+        let cast = world.shape_cast(&Shape::circle(0.1), 0.0, Vec2::new(11.5, 2.0), Vec2::new(9.5, 2.0), anything).unwrap();
+        assert_eq!(cast.body, near);
+        assert!((cast.fraction - 0.15).abs() < 1e-3);
+    }
+
+    #[test]
+    fn force_generators_use_the_worlds_bounds(){
+        //1 apart through the x edge, the first two push the pair apart and gravity pulls it together
+        let generators: Vec<(Box<dyn ForceGenerator>, f32)> = vec![
+            (Box::new(Coulomb::new(1.0).with_cutoff(2.0)), 1.0),
+            (Box::new(PairForce::new(Harmonic{ stiffness: 1.0, rest_length: 2.0 }, 2.0)), 1.0),
+            (Box::new(NBodyGravity::new(1.0)), -1.0),
+        ];
+        for (generator, direction) in generators{
+            let mut world = world();
+            let mut a = Body::new(Shape::circle(0.1), Vec2::new(0.5, 5.0));
+            a.charge = 1.0;
+            let mut b = Body::new(Shape::circle(0.1), Vec2::new(9.5, 5.0));
+            b.charge = 1.0;
+            let (a, b) = (world.add_body(a), world.add_body(b));
+            world.add_force_generator(generator);
+            world.step(1.0 / 60.0);
+            let (va, vb) = (world.body(a).unwrap().velocity, world.body(b).unwrap().velocity);
+            assert!(va.x * direction > 0.0 && vb.x * direction < 0.0, "{va:?} {vb:?}");
+            assert!(va.y.abs() < 1e-6 && vb.y.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn bodies_leaving_come_back_on_the_other_side(){
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let handle = world.add_body(Body::new(Shape::rect(1.0, 1.0), Vec2::new(5.0, 0.3)));
+        for _ in 0..60{
+            world.step(1.0 / 60.0);
+        }
+        let position = world.body(handle).unwrap().position;
+        assert!(position.y >= 0.0 && position.y < 10.0);
+    }
+}
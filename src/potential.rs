@@ -0,0 +1,271 @@
+use crate::body::Body;
+use crate::broadphase::SpatialGrid;
+use crate::filter::CollisionFilter;
+use crate::force::ForceGenerator;
+use crate::math::Vec2;
+use crate::periodic::{self, PeriodicBounds};
+use crate::shape::Aabb;
+
+pub trait PairPotential{
+    //-dU/dr, positive pushes the pair apart
+    fn force(&self, r: f32) -> f32;
+    fn energy(&self, r: f32) -> f32;
+}
+
+pub struct LennardJones{
+    //depth of the well
+    pub epsilon: f32,
+    //distance where the potential crosses zero
+    pub sigma: f32,
+}
+
+impl PairPotential for LennardJones{
+    fn force(&self, r: f32) -> f32{
+        let sr6 = (self.sigma / r).powi(6);
+        24.0 * self.epsilon * (2.0 * sr6 * sr6 - sr6) / r
+    }
+
+    fn energy(&self, r: f32) -> f32{
+        let sr6 = (self.sigma / r).powi(6);
+        4.0 * self.epsilon * (sr6 * sr6 - sr6)
+    }
+}
+
+pub struct Morse{
+    pub depth: f32,
+    //controls the width of the well, bigger is narrower
+    pub stiffness: f32,
+    pub equilibrium: f32,
+}
+
+impl PairPotential for Morse{
+    fn force(&self, r: f32) -> f32{
+        let e = (-self.stiffness * (r - self.equilibrium)).exp();
+        2.0 * self.depth * self.stiffness * (e * e - e)
+    }
+
+    fn energy(&self, r: f32) -> f32{
+        let e = 1.0 - (-self.stiffness * (r - self.equilibrium)).exp();
+        self.depth * (e * e - 1.0)
+    }
+}
+
+pub struct Harmonic{
+    pub stiffness: f32,
+    pub rest_length: f32,
+}
+
+impl PairPotential for Harmonic{
+    fn force(&self, r: f32) -> f32{
+        -self.stiffness * (r - self.rest_length)
+    }
+
+    fn energy(&self, r: f32) -> f32{
+        0.5 * self.stiffness * (r - self.rest_length) * (r - self.rest_length)
+    }
+}
+
+//soft particles should not also bounce off each other, bodies with this filter skip contacts among themselves
+pub fn soft_particle_filter(category: u32) -> CollisionFilter{
+    CollisionFilter::new(category, u32::MAX).with_group(-1)
+}
+
+//applies a pair potential between all bodies in the given categories, using a verlet neighbour list
+pub struct PairForce<P: PairPotential>{
+    pub potential: P,
+    pub cutoff: f32,
+    //extra distance kept in the neighbour list so it doesn't have to be rebuilt every step
+    pub skin: f32,
+    //bodies whose filter category shares a bit with this take part
+    pub categories: u32,
+    //bodies closer than this are treated as being this far apart, keeps the force finite
+    pub min_distance: f32,
+    grid: SpatialGrid,
+    neighbours: Vec<(usize, usize)>,
+    //positions and the world's periodic bounds at the time of the last rebuild
+    built_at: Vec<(usize, Vec2)>,
+    built_with: Option<PeriodicBounds>,
+}
+
+impl<P: PairPotential> PairForce<P>{
+    pub fn new(potential: P, cutoff: f32) -> Self{
+        Self{
+            potential,
+            cutoff,
+            skin: cutoff * 0.2,
+            categories: u32::MAX,
+            min_distance: cutoff * 0.01,
+            built_with: None,
+            grid: SpatialGrid::new(cutoff),
+            neighbours: Vec::new(),
+            built_at: Vec::new(),
+        }
+    }
+
+    fn participates(&self, body: &Body) -> bool{
+        body.filter.category & self.categories != 0
+    }
+
+    fn separation(&self, from: Vec2, to: Vec2) -> Vec2{
+        periodic::separation(&self.built_with, from, to)
+    }
+
+    pub fn neighbours(&self) -> &[(usize, usize)]{
+        &self.neighbours
+    }
+
+    fn needs_rebuild(&self, particles: &[(usize, Vec2)]) -> bool{
+        if particles.len() != self.built_at.len(){
+            return true;
+        }
+        let limit_sq = self.skin * self.skin * 0.25;
+        particles.iter().zip(self.built_at.iter())
+            .any(|((i, p), (j, q))| i != j || self.separation(*q, *p).length_squared() > limit_sq)
+    }
+
+    fn rebuild(&mut self, particles: Vec<(usize, Vec2)>){
+        let reach = self.cutoff + self.skin;
+        if self.grid.cell_size() != reach{
+            self.grid.set_cell_size(reach);
+        }
+        self.grid.clear();
+        let half = Vec2::new(reach * 0.5, reach * 0.5);
+        for (i, p) in particles.iter(){
+            let aabb = Aabb::new(*p - half, *p + half);
+            self.grid.insert(*i, aabb);
+            if let Some(periodic) = &self.built_with{
+                for offset in periodic.image_offsets(&aabb){
+                    self.grid.insert(*i, Aabb::new(aabb.min + offset, aabb.max + offset));
+                }
+            }
+        }
+        let positions: std::collections::HashMap<usize, Vec2> = particles.iter().copied().collect();
+        self.neighbours = self.grid.pairs().into_iter()
+            .filter(|(i, j)| self.separation(positions[i], positions[j]).length_squared() <= reach * reach)
+            .collect();
+        self.built_at = particles;
+    }
+
+    fn update_neighbours(&mut self, bodies: &[Option<Body>], periodic: &Option<PeriodicBounds>){
+        let particles: Vec<(usize, Vec2)> = bodies.iter().enumerate()
+            .filter_map(|(i, body)| body.as_ref().filter(|body| self.participates(body)).map(|body| (i, body.position)))
+            .collect();
+        if self.built_with != *periodic || self.needs_rebuild(&particles){
+            self.built_with = *periodic;
+            self.rebuild(particles);
+        }
+    }
+}
+
+impl<P: PairPotential> ForceGenerator for PairForce<P>{
+    fn apply(&mut self, bodies: &mut [Option<Body>], periodic: &Option<PeriodicBounds>, _dt: f32){
+        self.update_neighbours(bodies, periodic);
+        for &(i, j) in self.neighbours.iter(){
+            let (a, b) = match (&bodies[i], &bodies[j]){
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            let offset = self.separation(a.position, b.position);
+            let r = offset.length();
+            if r > self.cutoff || r < f32::EPSILON{
+                continue;
+            }
+            let force = offset / r * self.potential.force(r.max(self.min_distance));
+            for (index, sign) in [(i, -1.0), (j, 1.0)]{
+                let body = bodies[index].as_mut().unwrap();
+                if body.is_dynamic() && body.is_awake(){
+                    body.force += force * sign;
+                }
+            }
+        }
+    }
+
+    //total potential energy of all pairs within the cutoff
+    fn potential_energy(&mut self, bodies: &[Option<Body>], periodic: &Option<PeriodicBounds>) -> f32{
+        self.update_neighbours(bodies, periodic);
+        let mut energy = 0.0;
+        for &(i, j) in self.neighbours.iter(){
+            let (a, b) = match (&bodies[i], &bodies[j]){
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            let r = self.separation(a.position, b.position).length();
+            if r <= self.cutoff{
+                energy += self.potential.energy(r.max(self.min_distance)) - self.potential.energy(self.cutoff);
+            }
+        }
+        energy
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::shape::Shape;
+    use crate::world::World;
+
+    //a custom potential only has to implement the trait
+    struct Linear(f32);
+
+    impl PairPotential for Linear{
+        fn force(&self, _r: f32) -> f32{
+            self.0
+        }
+
+        fn energy(&self, r: f32) -> f32{
+            -self.0 * r
+        }
+    }
+
+    fn assert_force_is_slope(potential: &dyn PairPotential, r: f32){
+        let h = 1e-3;
+        let slope = (potential.energy(r + h) - potential.energy(r - h)) / (2.0 * h);
+        assert!((potential.force(r) + slope).abs() < 1e-2, "{} vs {}", potential.force(r), -slope);
+    }
+
+    #[test]
+    fn forces_match_their_energies(){
+        for r in [0.95, 1.3, 2.0]{
+            assert_force_is_slope(&LennardJones{ epsilon: 1.0, sigma: 1.0 }, r);
+            assert_force_is_slope(&Morse{ depth: 1.0, stiffness: 2.0, equilibrium: 1.0 }, r);
+            assert_force_is_slope(&Harmonic{ stiffness: 3.0, rest_length: 1.2 }, r);
+            assert_force_is_slope(&Linear(0.5), r);
+        }
+        let lennard_jones = LennardJones{ epsilon: 1.0, sigma: 1.0 };
+        assert!(lennard_jones.force(2.0f32.powf(1.0 / 6.0)).abs() < 1e-4);
+        assert!((lennard_jones.energy(2.0f32.powf(1.0 / 6.0)) + 1.0).abs() < 1e-4);
+    }
+
+    fn particles(world: &mut World, positions: &[Vec2]){
+        for p in positions{
+            let mut body = Body::new(Shape::circle(0.3), *p);
+            body.filter = soft_particle_filter(1);
+            world.add_body(body);
+        }
+    }
+
+    #[test]
+    fn neighbour_list_only_keeps_pairs_in_reach(){
+        let mut bodies: Vec<Option<Body>> = [0.0, 1.0, 5.0].iter().map(|x| Some(Body::new(Shape::circle(0.3), Vec2::new(*x, 0.0)))).collect();
+        let mut pairs = PairForce::new(Harmonic{ stiffness: 1.0, rest_length: 2.0 }, 2.0);
+        pairs.apply(&mut bodies, &None, 0.01);
+        assert_eq!(pairs.neighbours(), &[(0, 1)]);
+        //squeezed together, pushed apart
+        assert!(bodies[0].as_ref().unwrap().force.x < 0.0 && bodies[1].as_ref().unwrap().force.x > 0.0);
+        assert_eq!(bodies[2].as_ref().unwrap().force, Vec2::zero());
+    }
+
+    #[test]
+    fn lennard_jones_pair_settles_near_the_minimum(){
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        particles(&mut world, &[Vec2::zero(), Vec2::new(1.5, 0.0)]);
+        world.add_force_generator(Box::new(PairForce::new(LennardJones{ epsilon: 1.0, sigma: 1.0 }, 2.5)));
+        world.add_force_generator(Box::new(crate::force::LinearDrag{ coefficient: 0.5, angular_coefficient: 0.0 }));
+        for _ in 0..2000{
+            world.step(1.0 / 120.0);
+        }
+        let distance = world.separation(world.bodies().next().unwrap().1.position, world.bodies().nth(1).unwrap().1.position).length();
+        assert!((distance - 2.0f32.powf(1.0 / 6.0)).abs() < 0.02, "{}", distance);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::body::BodyHandle;
+use crate::collision;
+use crate::math::Vec2;
+use crate::shape::{self, Shape};
+
+//result of a ray or shape cast, fraction goes from 0 at the start to 1 at the end of the cast
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CastHit{
+    pub body: BodyHandle,
+    pub point: Vec2,
+    //surface normal of the hit body, facing the caster
+    pub normal: Vec2,
+    pub fraction: f32,
+}
+
+//(fraction, normal), rays starting inside a shape don't hit it
+pub fn raycast_shape(shape: &Shape, position: Vec2, angle: f32, from: Vec2, to: Vec2) -> Option<(f32, Vec2)>{
+    match shape{
+        Shape::Circle{ radius } => raycast_circle(position, *radius, from, to),
+        Shape::Polygon{ .. } => {
+            let vertices = shape.world_vertices(position, angle);
+            raycast_rounded_polygon(&vertices, 0.0, from, to)
+        },
+    }
+}
+
+pub fn raycast_circle(center: Vec2, radius: f32, from: Vec2, to: Vec2) -> Option<(f32, Vec2)>{
+    let d = to - from;
+    let m = from - center;
+    let c = m.length_squared() - radius * radius;
+    if c < 0.0{
+        return None;
+    }
+    let a = d.length_squared();
+    if a < f32::EPSILON{
+        return None;
+    }
+    let b = m.dot(d);
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0{
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / a;
+    if !(0.0..=1.0).contains(&t){
+        return None;
+    }
+    Some((t, (m + d * t).normalize()))
+}
+
+//ray against a convex polygon inflated by radius (a polygon with rounded corners)
+pub fn raycast_rounded_polygon(vertices: &[Vec2], radius: f32, from: Vec2, to: Vec2) -> Option<(f32, Vec2)>{
+    let d = to - from;
+    let count = vertices.len();
+    let mut lower = 0.0;
+    let mut upper = 1.0;
+    let mut edge = None;
+    for i in 0..count{
+        let v1 = vertices[i];
+        let v2 = vertices[(i + 1) % count];
+        let e = v2 - v1;
+        let normal = Vec2::new(e.y, -e.x).normalize();
+        let numerator = normal.dot(v1 - from) + radius;
+        let denominator = normal.dot(d);
+        if denominator == 0.0{
+            if numerator < 0.0{
+                return None;
+            }
+        } else if denominator < 0.0 && numerator < lower * denominator{
+            lower = numerator / denominator;
+            edge = Some(i);
+        } else if denominator > 0.0 && numerator < upper * denominator{
+            upper = numerator / denominator;
+        }
+        if upper < lower{
+            return None;
+        }
+    }
+    //starting inside
+    let edge = edge?;
+
+    let v1 = vertices[edge];
+    let v2 = vertices[(edge + 1) % count];
+    let e = v2 - v1;
+    let normal = Vec2::new(e.y, -e.x).normalize();
+    if radius <= 0.0{
+        return Some((lower, normal));
+    }
+    //the inflated edge was hit past its end, so the real hit is on the rounded corner
+    let along = (from + d * lower - v1).dot(e) / e.length_squared();
+    if along < 0.0{
+        raycast_circle(v1, radius, from, to)
+    } else if along > 1.0{
+        raycast_circle(v2, radius, from, to)
+    } else{
+        Some((lower, normal))
+    }
+}
+
+//moves shape from `from` to `to` without rotating it and reports the first touch with the target
+//(fraction, point, normal of the target)
+pub fn shape_cast(
+    shape: &Shape, angle: f32, from: Vec2, to: Vec2,
+    target: &Shape, target_position: Vec2, target_angle: f32,
+) -> Option<(f32, Vec2, Vec2)>{
+    //already overlapping at the start
+    if let Some((normal, points)) = collision::collide_shapes(target, target_position, target_angle, shape, from, angle){
+        return Some((0.0, points[0].point, normal));
+    }
+    let d = to - from;
+    match (shape, target){
+        (Shape::Circle{ radius }, Shape::Circle{ radius: target_radius }) => {
+            let (t, normal) = raycast_circle(target_position, radius + target_radius, from, to)?;
+            Some((t, from + d * t - normal * *radius, normal))
+        },
+        (Shape::Circle{ radius }, Shape::Polygon{ .. }) => {
+            let vertices = target.world_vertices(target_position, target_angle);
+            let (t, normal) = raycast_rounded_polygon(&vertices, *radius, from, to)?;
+            Some((t, from + d * t - normal * *radius, normal))
+        },
+        (Shape::Polygon{ .. }, Shape::Circle{ radius: target_radius }) => {
+            //same thing seen from the polygon, the circle center moves the other way
+            let vertices = shape.world_vertices(from, angle);
+            let (t, normal) = raycast_rounded_polygon(&vertices, *target_radius, target_position, target_position - d)?;
+            Some((t, target_position - normal * *target_radius, -normal))
+        },
+        (Shape::Polygon{ .. }, Shape::Polygon{ .. }) => {
+            //ray cast of the body position against the minkowski sum target + (-shape)
+            let local: Vec<Vec2> = shape.world_vertices(Vec2::zero(), angle);
+            let target_vertices = target.world_vertices(target_position, target_angle);
+            let mut sum = Vec::with_capacity(local.len() * target_vertices.len());
+            for t in target_vertices.iter(){
+                for l in local.iter(){
+                    sum.push(*t - *l);
+                }
+            }
+            let hull = shape::convex_hull(&sum);
+            let (t, normal) = raycast_rounded_polygon(&hull, 0.0, from, to)?;
+            //deepest point of the moving shape along the normal lies on the target surface
+            let support = local.iter().copied().max_by(|a, b| a.dot(-normal).total_cmp(&b.dot(-normal))).unwrap_or_default();
+            Some((t, from + d * t + support, normal))
+        },
+    }
+}
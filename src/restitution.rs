@@ -0,0 +1,161 @@
+use crate::body::{Body, BodyHandle};
+use crate::math::Vec2;
+
+//how much of the closing speed a contact gives back. the contact's restitution (combined from
+//the two bodies) is the starting point, the model decides what to make of it at a given impact speed
+
+//contacts closing slower than this don't bounce with the default model, otherwise resting bodies jitter forever
+pub const DEFAULT_THRESHOLD: f32 = 1.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RestitutionModel{
+    //full restitution above the impact speed, none below it
+    Threshold{ threshold: f32 },
+    //restitution scaled by a factor that depends on the impact speed. (speed, factor) points sorted
+    //by speed, linear in between and flat past the ends. real materials bounce less at low speeds
+    //(nothing to get over) and at high speeds (they deform)
+    Curve{ points: Vec<(f32, f32)> },
+    //perfectly inelastic, nothing ever bounces
+    Sticky,
+}
+
+impl Default for RestitutionModel{
+    fn default() -> Self{
+        RestitutionModel::Threshold{ threshold: DEFAULT_THRESHOLD }
+    }
+}
+
+impl RestitutionModel{
+    //sorts the points by speed
+    pub fn curve(points: &[(f32, f32)]) -> Self{
+        let mut points = points.to_vec();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        RestitutionModel::Curve{ points }
+    }
+
+    pub fn coefficient(&self, restitution: f32, speed: f32) -> f32{
+        match self{
+            RestitutionModel::Threshold{ threshold } => if speed > *threshold { restitution } else { 0.0 },
+            RestitutionModel::Curve{ points } => restitution * curve_factor(points, speed),
+            RestitutionModel::Sticky => 0.0,
+        }
+    }
+}
+
+fn curve_factor(points: &[(f32, f32)], speed: f32) -> f32{
+    let (first, last) = match (points.first(), points.last()){
+        (Some(first), Some(last)) => (first, last),
+        _ => return 1.0,
+    };
+    if speed <= first.0{
+        return first.1;
+    }
+    if speed >= last.0{
+        return last.1;
+    }
+    let i = points.windows(2).position(|pair| speed <= pair[1].0).unwrap_or(points.len() - 2);
+    let ((s0, f0), (s1, f1)) = (points[i], points[i + 1]);
+    if s1 - s0 <= f32::EPSILON{
+        return f1;
+    }
+    f0 + (f1 - f0) * (speed - s0) / (s1 - s0)
+}
+
+//what a restitution hook gets to look at, one call per contact point when the contact is set up
+pub struct Impact<'a>{
+    pub a: BodyHandle,
+    pub body_a: &'a Body,
+    pub b: BodyHandle,
+    pub body_b: &'a Body,
+    pub point: Vec2,
+    //from a to b
+    pub normal: Vec2,
+    //closing speed along the normal, positive when the bodies move towards each other
+    pub speed: f32,
+    //combined from the two bodies by the world's combine rules
+    pub restitution: f32,
+}
+
+//returns the restitution for the contact point, None leaves it to the world's model
+pub type RestitutionHook = Box<dyn Fn(&Impact) -> Option<f32>>;
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::shape::Shape;
+    use crate::world::World;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn curves_interpolate_and_stay_flat_past_the_ends(){
+        let model = RestitutionModel::curve(&[(10.0, 0.5), (0.5, 0.2), (2.0, 1.0)]);
+        assert_eq!(model, RestitutionModel::Curve{ points: vec![(0.5, 0.2), (2.0, 1.0), (10.0, 0.5)] });
+        assert!((model.coefficient(0.8, 1.25) - 0.8 * 0.6).abs() < 1e-6);
+        assert!((model.coefficient(0.8, 6.0) - 0.8 * 0.75).abs() < 1e-6);
+        assert_eq!(model.coefficient(0.8, 2.0), 0.8);
+        assert_eq!(model.coefficient(0.8, 0.0), 0.8 * 0.2);
+        assert_eq!(model.coefficient(0.8, 50.0), 0.8 * 0.5);
+        //without points the contact's restitution is used as it is
+        assert_eq!(RestitutionModel::curve(&[]).coefficient(0.8, 3.0), 0.8);
+    }
+
+    #[test]
+    fn default_only_bounces_fast_contacts(){
+        let model = RestitutionModel::default();
+        assert_eq!(model.coefficient(0.7, 0.5), 0.0);
+        assert_eq!(model.coefficient(0.7, 1.0), 0.0);
+        assert_eq!(model.coefficient(0.7, 1.5), 0.7);
+        assert_eq!(RestitutionModel::Sticky.coefficient(0.7, 10.0), 0.0);
+    }
+
+    //drops a ball on the floor and returns how fast it comes back up
+    fn bounce(world: &mut World) -> f32{
+        world.sleep.enabled = false;
+        world.add_body(Body::new_static(Shape::rect(10.0, 1.0), Vec2::new(0.0, -0.5)));
+        let mut ball = Body::new(Shape::circle(0.5), Vec2::new(0.0, 3.0));
+        ball.restitution = 1.0;
+        let ball = world.add_body(ball);
+        let mut fastest: f32 = 0.0;
+        for _ in 0..60{
+            world.step(1.0 / 60.0);
+            fastest = fastest.max(world.body(ball).unwrap().velocity.y);
+        }
+        fastest
+    }
+
+    #[test]
+    fn sticky_worlds_never_bounce(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        world.restitution_model = RestitutionModel::Sticky;
+        assert!(bounce(&mut world).abs() < 0.05);
+        //the same drop does bounce with the default model
+        assert!(bounce(&mut World::new(Vec2::new(0.0, -9.81))) > 1.0);
+    }
+
+    #[test]
+    fn hooks_fall_back_to_the_model(){
+        let model = RestitutionModel::curve(&[(0.0, 0.0), (20.0, 1.0)]);
+        let mut plain = World::new(Vec2::new(0.0, -9.81));
+        plain.restitution_model = model.clone();
+        let expected = bounce(&mut plain);
+        assert!(expected > 1.0);
+
+        let calls = Rc::new(Cell::new(0));
+        let mut hooked = World::new(Vec2::new(0.0, -9.81));
+        hooked.restitution_model = model.clone();
+        let counter = calls.clone();
+        hooked.set_restitution_hook(Box::new(move |impact| {
+            counter.set(counter.get() + 1);
+            assert!(impact.speed > 0.0 && impact.restitution == 1.0);
+            None
+        }));
+        assert_eq!(bounce(&mut hooked), expected);
+        assert!(calls.get() > 0);
+
+        let mut overridden = World::new(Vec2::new(0.0, -9.81));
+        overridden.restitution_model = model;
+        overridden.set_restitution_hook(Box::new(|_| Some(0.0)));
+        assert!(bounce(&mut overridden) < 0.1);
+    }
+}
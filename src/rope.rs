@@ -0,0 +1,319 @@
+use crate::body::{Body, BodyHandle};
+use crate::filter::CollisionFilter;
+use crate::math::Vec2;
+use crate::path;
+use crate::shape::Shape;
+use crate::spring::{Anchor, Spring};
+use crate::world::World;
+
+//ropes and chains are a line of segments held together by stiff springs between their joints.
+//a rope is made of circles joined at their centers, a chain of thin boxes joined end to end
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RopeStyle{
+    Rope,
+    Chain,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rope{
+    pub segments: Vec<BodyHandle>,
+    pub start: Option<Anchor>,
+    pub end: Option<Anchor>,
+    //where each segment is joined to its neighbours, in the segment's own frame
+    joints: Vec<(Vec2, Vec2)>,
+}
+
+fn anchor_position(world: &World, anchor: &Anchor) -> Option<Vec2>{
+    match anchor{
+        Anchor::Body{ body, local } => world.body(*body).map(|body| body.position + local.rotate(body.angle)),
+        Anchor::World(point) => Some(*point),
+    }
+}
+
+impl Rope{
+    fn joint_position(&self, world: &World, segment: usize, end: bool) -> Option<Vec2>{
+        let body = world.body(self.segments[segment])?;
+        let (start_joint, end_joint) = self.joints[segment];
+        let local = if end { end_joint } else { start_joint };
+        Some(body.position + local.rotate(body.angle))
+    }
+
+    //the anchors (or the free ends) with the segment centers in between, segments that are gone are skipped
+    pub fn points(&self, world: &World) -> Vec<Vec2>{
+        if self.segments.is_empty(){
+            return Vec::new();
+        }
+        let last = self.segments.len() - 1;
+        let mut points = Vec::with_capacity(self.segments.len() + 2);
+        let start = self.start.as_ref().and_then(|anchor| anchor_position(world, anchor));
+        points.extend(start.or_else(|| self.joint_position(world, 0, false)));
+        points.extend(self.segments.iter().filter_map(|handle| world.body(*handle)).map(|body| body.position));
+        let end = self.end.as_ref().and_then(|anchor| anchor_position(world, anchor));
+        points.extend(end.or_else(|| self.joint_position(world, last, true)));
+        points
+    }
+
+    //catmull-rom curve through the points with `subdivisions` lines per span, for drawing as a line strip
+    pub fn polyline(&self, world: &World, subdivisions: usize) -> Vec<Vec2>{
+        let points = self.points(world);
+        if points.len() < 2{
+            return points;
+        }
+        let subdivisions = subdivisions.max(1);
+        let at = |index: isize| points[index.clamp(0, points.len() as isize - 1) as usize];
+        let mut line = Vec::with_capacity((points.len() - 1) * subdivisions + 1);
+        for i in 0..(points.len() as isize - 1){
+            for step in 0..subdivisions{
+                let u = step as f32 / subdivisions as f32;
+                line.push(path::catmull_rom(at(i - 1), at(i), at(i + 1), at(i + 2), u));
+            }
+        }
+        line.push(points[points.len() - 1]);
+        line
+    }
+
+    pub fn length(&self, world: &World) -> f32{
+        self.points(world).windows(2).map(|pair| (pair[1] - pair[0]).length()).sum()
+    }
+}
+
+pub struct RopeBuilder{
+    pub from: Vec2,
+    pub to: Vec2,
+    pub segment_count: usize,
+    pub style: RopeStyle,
+    //of the whole rope, split evenly over the segments
+    pub mass: f32,
+    //circle diameter for a rope, link height for a chain. defaults depend on the style
+    pub thickness: Option<f32>,
+    //a spring can't pull harder in a step than its ends can follow, so with light segments a very high
+    //stiffness still ends up stretchy. more mass per segment or smaller steps make a tighter rope
+    pub stiffness: f32,
+    pub damping: f32,
+    pub start: Option<Anchor>,
+    pub end: Option<Anchor>,
+    //neighbouring links can touch when a chain bends hard, a negative group turns that off
+    pub filter: CollisionFilter,
+    pub color: [f32; 3],
+}
+
+impl RopeBuilder{
+    pub fn new(from: Vec2, to: Vec2, segment_count: usize) -> Self{
+        Self{
+            from,
+            to,
+            segment_count: segment_count.max(1),
+            style: RopeStyle::Rope,
+            mass: 1.0,
+            thickness: None,
+            stiffness: 100.0,
+            damping: 0.5,
+            start: None,
+            end: None,
+            filter: CollisionFilter::default(),
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn chain(from: Vec2, to: Vec2, segment_count: usize) -> Self{
+        Self::new(from, to, segment_count).with_style(RopeStyle::Chain)
+    }
+
+    pub fn with_style(mut self, style: RopeStyle) -> Self{
+        self.style = style;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f32) -> Self{
+        self.mass = mass;
+        self
+    }
+
+    pub fn with_thickness(mut self, thickness: f32) -> Self{
+        self.thickness = Some(thickness);
+        self
+    }
+
+    pub fn with_springs(mut self, stiffness: f32, damping: f32) -> Self{
+        self.stiffness = stiffness;
+        self.damping = damping;
+        self
+    }
+
+    //ties the first segment to the anchor, the spring's rest length is the distance at build time
+    pub fn with_start(mut self, anchor: Anchor) -> Self{
+        self.start = Some(anchor);
+        self
+    }
+
+    pub fn with_end(mut self, anchor: Anchor) -> Self{
+        self.end = Some(anchor);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: CollisionFilter) -> Self{
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self{
+        self.color = color;
+        self
+    }
+
+    fn spacing(&self) -> f32{
+        (self.to - self.from).length() / self.segment_count as f32
+    }
+
+    //(shape, joint at the start, joint at the end) in the segment's frame, x runs along the rope
+    fn segment(&self) -> (Shape, Vec2, Vec2){
+        let spacing = self.spacing();
+        match self.style{
+            RopeStyle::Rope => {
+                //a bit under the spacing so neighbours don't rub
+                let diameter = self.thickness.unwrap_or(spacing * 0.9);
+                (Shape::circle(diameter * 0.5), Vec2::zero(), Vec2::zero())
+            },
+            RopeStyle::Chain => {
+                let length = spacing * 0.8;
+                let height = self.thickness.unwrap_or(spacing * 0.25);
+                let half = Vec2::new(length * 0.5, 0.0);
+                (Shape::rect(length, height), -half, half)
+            },
+        }
+    }
+
+    pub fn build(&self, world: &mut World) -> Rope{
+        let offset = self.to - self.from;
+        let direction = offset.normalize();
+        let angle = direction.y.atan2(direction.x);
+        let spacing = self.spacing();
+        let (shape, start_joint, end_joint) = self.segment();
+        let density = self.mass / self.segment_count as f32 / shape.area();
+
+        let segments: Vec<BodyHandle> = (0..self.segment_count).map(|i| {
+            let mut body = Body::new(shape.clone(), self.from + direction * (spacing * (i as f32 + 0.5)));
+            body.angle = angle;
+            body.set_density(density);
+            if self.style == RopeStyle::Rope{
+                //round segments have nothing to hold their spin, it would only keep the rope awake
+                body.set_fixed_rotation(true);
+            }
+            body.filter = self.filter;
+            body.color = self.color;
+            world.add_body(body)
+        }).collect();
+        let rope = Rope{
+            segments,
+            start: self.start,
+            end: self.end,
+            joints: vec![(start_joint, end_joint); self.segment_count],
+        };
+
+        let mut links = Vec::with_capacity(self.segment_count + 1);
+        for i in 1..self.segment_count{
+            links.push((Anchor::Body{ body: rope.segments[i - 1], local: end_joint }, Anchor::Body{ body: rope.segments[i], local: start_joint }));
+        }
+        if let Some(anchor) = self.start{
+            links.push((anchor, Anchor::Body{ body: rope.segments[0], local: start_joint }));
+        }
+        if let Some(anchor) = self.end{
+            links.push((Anchor::Body{ body: rope.segments[self.segment_count - 1], local: end_joint }, anchor));
+        }
+        for (a, b) in links{
+            let rest_length = match (anchor_position(world, &a), anchor_position(world, &b)){
+                (Some(pa), Some(pb)) => (pb - pa).length(),
+                _ => continue,
+            };
+            world.add_spring(Spring::new(a, b, rest_length, self.stiffness).with_damping(self.damping));
+        }
+        rope
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn run(world: &mut World, steps: usize){
+        for _ in 0..steps{
+            world.step(DT);
+        }
+    }
+
+    #[test]
+    fn ropes_are_tied_together_and_to_their_anchors(){
+        let mut world = World::new(Vec2::zero());
+        let rope = RopeBuilder::new(Vec2::zero(), Vec2::new(5.0, 0.0), 10)
+            .with_start(Anchor::World(Vec2::zero()))
+            .with_end(Anchor::World(Vec2::new(5.0, 0.0)))
+            .build(&mut world);
+        assert_eq!(rope.segments.len(), 10);
+        assert_eq!(world.springs().len(), 11);
+        let points = rope.points(&world);
+        assert_eq!(points.len(), 12);
+        assert!((rope.length(&world) - 5.0).abs() < 1e-4);
+        //the curve goes through every point
+        let line = rope.polyline(&world, 4);
+        assert_eq!(line.len(), 11 * 4 + 1);
+        for (i, point) in points.iter().enumerate(){
+            assert!((line[i * 4] - *point).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn hanging_ropes_stretch_under_their_own_weight(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        let anchor = Vec2::new(0.0, 10.0);
+        let (mass, stiffness, damping) = (4.0, 600.0, 2.0);
+        let rope = RopeBuilder::new(anchor, Vec2::new(0.0, 6.0), 16)
+            .with_start(Anchor::World(anchor))
+            .with_mass(mass)
+            .with_springs(stiffness, damping)
+            .build(&mut world);
+        run(&mut world, 300);
+        //it bounces for a long time, so the length is averaged
+        let mut length = 0.0;
+        for _ in 0..600{
+            world.step(DT);
+            length += rope.length(&world) / 600.0;
+        }
+        let points = rope.points(&world);
+        assert_eq!(points[0], anchor);
+        assert!(points.iter().all(|point| point.x.abs() < 1e-3));
+        //spring i from the bottom holds up i segments, (m g / k) (1 + 2 + ... + 16) / 16 in all. the
+        //implicit step softens a spring by up to 1 + dt inv_mass (damping + stiffness dt) on top of that
+        let stretch = mass * 9.81 / stiffness * 17.0 / 2.0;
+        let softening = 1.0 + DT * 2.0 / (mass / 16.0) * (damping + stiffness * DT);
+        assert!(length > 4.0 + stretch && length < 4.0 + stretch * softening, "{length}");
+    }
+
+    #[test]
+    fn chains_are_links_joined_end_to_end(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        let anchor = Vec2::new(0.0, 5.0);
+        let chain = RopeBuilder::chain(anchor, Vec2::new(3.0, 5.0), 6)
+            .with_start(Anchor::World(anchor))
+            .with_filter(CollisionFilter::default().with_group(-1))
+            .build(&mut world);
+        let first = world.body(chain.segments[0]).unwrap();
+        assert!(matches!(first.shape, Shape::Polygon{ .. }));
+        assert!((chain.points(&world)[1] - Vec2::new(0.25, 5.0)).length() < 1e-4);
+        run(&mut world, 600);
+        let end = *chain.points(&world).last().unwrap();
+        assert!(end.y < 3.0 && end.y.is_finite());
+    }
+
+    #[test]
+    fn removed_segments_are_skipped(){
+        let mut world = World::new(Vec2::zero());
+        let rope = RopeBuilder::new(Vec2::zero(), Vec2::new(4.0, 0.0), 4).build(&mut world);
+        world.remove_body(rope.segments[1]);
+        assert_eq!(rope.points(&world).len(), 5);
+        //the spring on each side of the gap went with the segment
+        assert_eq!(world.springs().len(), 1);
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+
+use crate::body::BodyHandle;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SensorEventKind{
+    Enter,
+    Stay,
+    Exit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SensorEvent{
+    pub sensor: BodyHandle,
+    pub body: BodyHandle,
+    pub kind: SensorEventKind,
+}
+
+//remembers which bodies were inside which sensor last step so we can tell enters from exits
+#[derive(Default)]
+pub struct SensorTracker{
+    overlaps: HashSet<(BodyHandle, BodyHandle)>,
+    events: Vec<SensorEvent>,
+    //exits of bodies removed between steps, handed out with the next step's events
+    removed: Vec<SensorEvent>,
+}
+
+impl SensorTracker{
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    pub fn events(&self) -> &[SensorEvent]{
+        &self.events
+    }
+
+    pub fn is_overlapping(&self, sensor: BodyHandle, body: BodyHandle) -> bool{
+        self.overlaps.contains(&(sensor, body))
+    }
+
+    pub fn overlaps_of(&self, sensor: BodyHandle) -> impl Iterator<Item = BodyHandle> + '_{
+        self.overlaps.iter().filter(move |(s, _)| *s == sensor).map(|(_, body)| *body)
+    }
+
+    //current holds every (sensor, body) pair overlapping this step
+    pub fn update(&mut self, current: HashSet<(BodyHandle, BodyHandle)>){
+        self.events.clear();
+        self.events.append(&mut self.removed);
+        for &(sensor, body) in current.iter(){
+            let kind = if self.overlaps.contains(&(sensor, body)) { SensorEventKind::Stay } else { SensorEventKind::Enter };
+            self.events.push(SensorEvent{
+                sensor,
+                body,
+                kind,
+            });
+        }
+        for &(sensor, body) in self.overlaps.difference(&current){
+            self.events.push(SensorEvent{
+                sensor,
+                body,
+                kind: SensorEventKind::Exit,
+            });
+        }
+        //hash set order is random, keep the event order stable between runs
+        self.events.sort_by_key(|e| (e.sensor, e.body));
+        self.overlaps = current;
+    }
+
+    //a removed body leaves every sensor it was in (or stops being a sensor), the exits show up
+    //after the next update
+    pub fn remove_body(&mut self, handle: BodyHandle){
+        let mut removed: Vec<(BodyHandle, BodyHandle)> = self.overlaps.iter()
+            .filter(|(sensor, body)| *sensor == handle || *body == handle)
+            .copied()
+            .collect();
+        removed.sort();
+        for (sensor, body) in removed{
+            self.overlaps.remove(&(sensor, body));
+            self.removed.push(SensorEvent{
+                sensor,
+                body,
+                kind: SensorEventKind::Exit,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::body::Body;
+    use crate::math::Vec2;
+    use crate::shape::Shape;
+    use crate::world::World;
+
+    fn kinds(tracker: &SensorTracker) -> Vec<SensorEventKind>{
+        tracker.events().iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn tracker_tells_enter_stay_and_exit_apart(){
+        let (sensor, body) = (BodyHandle(0), BodyHandle(1));
+        let mut tracker = SensorTracker::new();
+        tracker.update(HashSet::from([(sensor, body)]));
+        assert_eq!(kinds(&tracker), vec![SensorEventKind::Enter]);
+        tracker.update(HashSet::from([(sensor, body)]));
+        assert_eq!(kinds(&tracker), vec![SensorEventKind::Stay]);
+        assert!(tracker.is_overlapping(sensor, body));
+        tracker.update(HashSet::new());
+        assert_eq!(kinds(&tracker), vec![SensorEventKind::Exit]);
+        assert_eq!(tracker.overlaps_of(sensor).count(), 0);
+
+        tracker.update(HashSet::from([(sensor, body)]));
+        tracker.remove_body(body);
+        assert_eq!(kinds(&tracker), vec![SensorEventKind::Enter]);
+        assert!(!tracker.is_overlapping(sensor, body));
+        tracker.update(HashSet::new());
+        assert_eq!(kinds(&tracker), vec![SensorEventKind::Exit]);
+    }
+
+    #[test]
+    fn falling_body_passes_through_a_sensor(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        let sensor = world.add_body(Body::new_sensor(Shape::rect(4.0, 1.0), Vec2::zero()));
+        let ball = world.add_body(Body::new(Shape::circle(0.2), Vec2::new(0.0, 2.0)));
+        let mut seen = Vec::new();
+        let mut stays = 0;
+        for _ in 0..120{
+            world.step(1.0 / 60.0);
+            for event in world.sensor_events(){
+                assert_eq!((event.sensor, event.body), (sensor, ball));
+                match event.kind{
+                    SensorEventKind::Stay => stays += 1,
+                    kind => seen.push(kind),
+                }
+            }
+        }
+        assert_eq!(seen, vec![SensorEventKind::Enter, SensorEventKind::Exit]);
+        assert!(stays > 0);
+        //the sensor never pushed it
+        assert!(world.body(ball).unwrap().position.y < -2.0);
+    }
+
+    #[test]
+    fn removing_a_body_between_steps_exits_it_on_the_next_step(){
+        let mut world = World::new(Vec2::zero());
+        let sensor = world.add_body(Body::new_sensor(Shape::rect(4.0, 4.0), Vec2::zero()));
+        let inside = world.add_body(Body::new(Shape::circle(0.2), Vec2::new(-1.0, 0.0)));
+        let other = world.add_body(Body::new(Shape::circle(0.2), Vec2::new(1.0, 0.0)));
+        world.step(1.0 / 60.0);
+        assert_eq!(world.sensor_events().len(), 2);
+        world.remove_body(inside);
+        world.step(1.0 / 60.0);
+        let events: Vec<(BodyHandle, SensorEventKind)> = world.sensor_events().iter().map(|e| (e.body, e.kind)).collect();
+        assert_eq!(events, vec![(inside, SensorEventKind::Exit), (other, SensorEventKind::Stay)]);
+        assert_eq!(world.bodies_in_sensor(sensor), vec![other]);
+        //only once
+        world.step(1.0 / 60.0);
+        assert!(world.sensor_events().iter().all(|e| e.kind == SensorEventKind::Stay));
+    }
+}
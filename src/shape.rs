@@ -0,0 +1,254 @@
+use crate::math::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb{
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb{
+    pub fn new(min: Vec2, max: Vec2) -> Self{
+        Self{
+            min,
+            max,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool{
+        self.min.x <= other.max.x && self.max.x >= other.min.x &&
+        self.min.y <= other.max.y && self.max.y >= other.min.y
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool{
+        point.x >= self.min.x && point.x <= self.max.x &&
+        point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb{
+        Aabb::new(
+            Vec2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            Vec2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        )
+    }
+
+    pub fn center(&self) -> Vec2{
+        (self.min + self.max) * 0.5
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Shape{
+    Circle{
+        radius: f32,
+    },
+    //vertices are counter-clockwise and relative to the centroid
+    Polygon{
+        vertices: Vec<Vec2>,
+        normals: Vec<Vec2>,
+    },
+}
+
+impl Shape{
+    pub fn circle(radius: f32) -> Self{
+        Shape::Circle{
+            radius,
+        }
+    }
+
+    //NOTE: the vertices get moved so that the centroid ends up at the origin,
+    //the body position is always the center of mass
+    pub fn polygon(points: &[Vec2]) -> Self{
+        assert!(points.len() >= 3, "a polygon needs at least 3 vertices");
+        let mut vertices = points.to_vec();
+        if signed_area(&vertices) < 0.0{
+            vertices.reverse();
+        }
+        let centroid = polygon_centroid(&vertices);
+        for v in vertices.iter_mut(){
+            *v -= centroid;
+        }
+        let normals = (0..vertices.len()).map(|i| {
+            let edge = vertices[(i + 1) % vertices.len()] - vertices[i];
+            Vec2::new(edge.y, -edge.x).normalize()
+        }).collect();
+        Shape::Polygon{
+            vertices,
+            normals,
+        }
+    }
+
+    pub fn rect(width: f32, height: f32) -> Self{
+        let hw = width * 0.5;
+        let hh = height * 0.5;
+        Shape::polygon(&[
+            Vec2::new(-hw, -hh),
+            Vec2::new(hw, -hh),
+            Vec2::new(hw, hh),
+            Vec2::new(-hw, hh),
+        ])
+    }
+
+    pub fn area(&self) -> f32{
+        match self{
+            Shape::Circle{ radius } => std::f32::consts::PI * radius * radius,
+            Shape::Polygon{ vertices, .. } => signed_area(vertices),
+        }
+    }
+
+    //returns (mass, rotational inertia about the centroid)
+    pub fn mass_data(&self, density: f32) -> (f32, f32){
+        match self{
+            Shape::Circle{ radius } => {
+                let mass = density * self.area();
+                (mass, 0.5 * mass * radius * radius)
+            },
+            Shape::Polygon{ vertices, .. } => {
+                let mut mass = 0.0;
+                let mut inertia = 0.0;
+                for i in 0..vertices.len(){
+                    let p1 = vertices[i];
+                    let p2 = vertices[(i + 1) % vertices.len()];
+                    let cross = p1.cross(p2);
+                    mass += 0.5 * cross * density;
+                    inertia += density * cross * (p1.dot(p1) + p1.dot(p2) + p2.dot(p2)) / 12.0;
+                }
+                (mass, inertia)
+            },
+        }
+    }
+
+    pub fn aabb(&self, position: Vec2, angle: f32) -> Aabb{
+        match self{
+            Shape::Circle{ radius } => {
+                let r = Vec2::new(*radius, *radius);
+                Aabb::new(position - r, position + r)
+            },
+            Shape::Polygon{ vertices, .. } => {
+                let mut min = Vec2::new(f32::MAX, f32::MAX);
+                let mut max = Vec2::new(f32::MIN, f32::MIN);
+                for v in vertices{
+                    let v = position + v.rotate(angle);
+                    min = Vec2::new(min.x.min(v.x), min.y.min(v.y));
+                    max = Vec2::new(max.x.max(v.x), max.y.max(v.y));
+                }
+                Aabb::new(min, max)
+            },
+        }
+    }
+
+    //largest distance from the centroid to any point of the shape
+    pub fn bounding_radius(&self) -> f32{
+        match self{
+            Shape::Circle{ radius } => *radius,
+            Shape::Polygon{ vertices, .. } => vertices.iter().map(|v| v.length()).fold(0.0, f32::max),
+        }
+    }
+
+    pub fn world_vertices(&self, position: Vec2, angle: f32) -> Vec<Vec2>{
+        match self{
+            Shape::Circle{ .. } => Vec::new(),
+            Shape::Polygon{ vertices, .. } => vertices.iter().map(|v| position + v.rotate(angle)).collect(),
+        }
+    }
+
+    pub fn world_normals(&self, angle: f32) -> Vec<Vec2>{
+        match self{
+            Shape::Circle{ .. } => Vec::new(),
+            Shape::Polygon{ normals, .. } => normals.iter().map(|n| n.rotate(angle)).collect(),
+        }
+    }
+
+    pub fn contains_point(&self, position: Vec2, angle: f32, point: Vec2) -> bool{
+        let local = (point - position).rotate(-angle);
+        match self{
+            Shape::Circle{ radius } => local.length_squared() <= radius * radius,
+            Shape::Polygon{ vertices, normals } => {
+                vertices.iter().zip(normals).all(|(v, n)| n.dot(local - *v) <= 0.0)
+            },
+        }
+    }
+}
+
+pub fn signed_area(vertices: &[Vec2]) -> f32{
+    let mut area = 0.0;
+    for i in 0..vertices.len(){
+        area += vertices[i].cross(vertices[(i + 1) % vertices.len()]);
+    }
+    area * 0.5
+}
+
+pub fn polygon_centroid(vertices: &[Vec2]) -> Vec2{
+    let mut centroid = Vec2::zero();
+    let mut area = 0.0;
+    for i in 0..vertices.len(){
+        let p1 = vertices[i];
+        let p2 = vertices[(i + 1) % vertices.len()];
+        let cross = p1.cross(p2);
+        area += cross * 0.5;
+        centroid += (p1 + p2) * (cross / 6.0);
+    }
+    if area.abs() > f32::EPSILON{
+        centroid / area
+    } else{
+        vertices.iter().fold(Vec2::zero(), |acc, v| acc + *v) / vertices.len() as f32
+    }
+}
+
+//keeps the side of the line through `point` that `normal` points away from
+pub fn clip(polygon: &[Vec2], point: Vec2, normal: Vec2) -> Vec<Vec2>{
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for i in 0..polygon.len(){
+        let current = polygon[i];
+        let next = polygon[(i + 1) % polygon.len()];
+        let dc = (current - point).dot(normal);
+        let dn = (next - point).dot(normal);
+        if dc <= 0.0{
+            clipped.push(current);
+        }
+        if (dc < 0.0 && dn > 0.0) || (dc > 0.0 && dn < 0.0){
+            clipped.push(current + (next - current) * (dc / (dc - dn)));
+        }
+    }
+    clipped
+}
+
+//part of the polygon inside a convex counter-clockwise outline
+pub fn clip_convex(polygon: &[Vec2], outline: &[Vec2]) -> Vec<Vec2>{
+    let mut clipped = polygon.to_vec();
+    for i in 0..outline.len(){
+        if clipped.len() < 3{
+            return Vec::new();
+        }
+        let from = outline[i];
+        let edge = outline[(i + 1) % outline.len()] - from;
+        //outward normal of a counter-clockwise edge
+        clipped = clip(&clipped, from, Vec2::new(edge.y, -edge.x));
+    }
+    clipped
+}
+
+//counter-clockwise convex hull (monotone chain), collinear points are dropped
+pub fn convex_hull(points: &[Vec2]) -> Vec<Vec2>{
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3{
+        return points;
+    }
+    let mut hull = half_hull(points.iter());
+    hull.extend(half_hull(points.iter().rev()));
+    hull
+}
+
+//one side of the hull, without its last point since the other side starts there
+fn half_hull<'a>(points: impl Iterator<Item = &'a Vec2>) -> Vec<Vec2>{
+    let mut chain: Vec<Vec2> = Vec::new();
+    for p in points{
+        while chain.len() >= 2 && (chain[chain.len() - 1] - chain[chain.len() - 2]).cross(*p - chain[chain.len() - 2]) <= 0.0{
+            chain.pop();
+        }
+        chain.push(*p);
+    }
+    chain.pop();
+    chain
+}
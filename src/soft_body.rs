@@ -0,0 +1,380 @@
+use crate::body::{Body, BodyHandle};
+use crate::force::ForceGenerator;
+use crate::math::Vec2;
+use crate::periodic::PeriodicBounds;
+use crate::shape::{self, Shape};
+use crate::spring::{Anchor, Spring};
+use crate::world::World;
+
+//squishy objects made of small circles tied together with springs. the circles do the colliding,
+//the springs hold the shape and an optional pressure inside the hull keeps it from folding up
+
+#[derive(Clone, Debug)]
+pub struct SoftBody{
+    pub particles: Vec<BodyHandle>,
+    //outline particles, counter clockwise
+    pub hull: Vec<BodyHandle>,
+    pub rest_area: f32,
+}
+
+impl SoftBody{
+    pub fn hull_polygon(&self, world: &World) -> Vec<Vec2>{
+        self.hull.iter().filter_map(|handle| world.body(*handle)).map(|body| body.position).collect()
+    }
+
+    pub fn area(&self, world: &World) -> f32{
+        shape::signed_area(&self.hull_polygon(world))
+    }
+
+    //center of mass of all the particles
+    pub fn center(&self, world: &World) -> Vec2{
+        let (mut sum, mut mass) = (Vec2::zero(), 0.0);
+        for body in self.particles.iter().filter_map(|handle| world.body(*handle)){
+            sum += body.position * body.mass();
+            mass += body.mass();
+        }
+        if mass > 0.0 { sum / mass } else { Vec2::zero() }
+    }
+
+    //the filled hull as a triangle fan around the center, ready for the renderer.
+    //the hull can go concave when squashed, fanning from the center still covers it for blob-like shapes
+    pub fn hull_triangles(&self, world: &World) -> Vec<[Vec2; 3]>{
+        let hull = self.hull_polygon(world);
+        let center = self.center(world);
+        (0..hull.len()).map(|i| [center, hull[i], hull[(i + 1) % hull.len()]]).collect()
+    }
+}
+
+//pushes the hull particles outwards along the edge normals, goes away with the first hull particle removed
+pub struct HullPressure{
+    pub hull: Vec<BodyHandle>,
+    pub rest_area: f32,
+    //gas pressure at the rest area, goes up as the area shrinks (P A = const)
+    pub pressure: f32,
+    //extra pressure proportional to the relative change in area
+    pub area_stiffness: f32,
+}
+
+impl HullPressure{
+    fn polygon(&self, bodies: &[Option<Body>]) -> Option<Vec<Vec2>>{
+        self.hull.iter().map(|handle| bodies.get(handle.0)?.as_ref().map(|body| body.position)).collect()
+    }
+
+    fn pressure_at(&self, area: f32) -> f32{
+        let mut pressure = self.area_stiffness * (self.rest_area - area) / self.rest_area;
+        if area > 0.0{
+            pressure += self.pressure * self.rest_area / area;
+        }
+        pressure
+    }
+}
+
+impl ForceGenerator for HullPressure{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, _dt: f32){
+        let polygon = match self.polygon(bodies){
+            Some(polygon) if polygon.len() >= 3 && self.rest_area > 0.0 => polygon,
+            _ => return,
+        };
+        let pressure = self.pressure_at(shape::signed_area(&polygon));
+        for i in 0..polygon.len(){
+            let j = (i + 1) % polygon.len();
+            let edge = polygon[j] - polygon[i];
+            //outward normal scaled by the edge length, half goes to each end
+            let force = Vec2::new(edge.y, -edge.x) * (pressure * 0.5);
+            for index in [self.hull[i].0, self.hull[j].0]{
+                let body = bodies[index].as_mut().unwrap();
+                if body.is_dynamic() && body.is_awake(){
+                    body.force += force;
+                }
+            }
+        }
+    }
+
+    fn potential_energy(&mut self, bodies: &[Option<Body>], _periodic: &Option<PeriodicBounds>) -> f32{
+        let area = match self.polygon(bodies){
+            Some(polygon) if polygon.len() >= 3 && self.rest_area > 0.0 => shape::signed_area(&polygon),
+            _ => return 0.0,
+        };
+        let change = area - self.rest_area;
+        let mut energy = 0.5 * self.area_stiffness * change * change / self.rest_area;
+        if area > 0.0{
+            energy -= self.pressure * self.rest_area * (area / self.rest_area).ln();
+        }
+        energy
+    }
+
+    fn body_removed(&mut self, handle: BodyHandle) -> bool{
+        !self.hull.contains(&handle)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoftBodyShape{
+    //particles around a circle
+    Ring{ center: Vec2, radius: f32, count: usize },
+    //columns x rows particles, origin is the bottom left one
+    Grid{ origin: Vec2, columns: usize, rows: usize, spacing: f32 },
+}
+
+pub struct SoftBodyBuilder{
+    pub shape: SoftBodyShape,
+    //defaults to a bit under half the particle spacing so neighbours don't touch
+    pub particle_radius: Option<f32>,
+    pub particle_mass: f32,
+    //springs are stepped one at a time, so a particle tied by n of them blows up once
+    //n * stiffness * dt^2 / particle_mass gets past about 2. grid particles have 8
+    pub stiffness: f32,
+    pub damping: f32,
+    pub pressure: f32,
+    pub area_stiffness: f32,
+    pub color: [f32; 3],
+}
+
+impl SoftBodyBuilder{
+    fn new(shape: SoftBodyShape) -> Self{
+        Self{
+            shape,
+            particle_radius: None,
+            particle_mass: 0.1,
+            stiffness: 50.0,
+            damping: 1.0,
+            pressure: 0.0,
+            area_stiffness: 0.0,
+            color: [1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn ring(center: Vec2, radius: f32, count: usize) -> Self{
+        Self::new(SoftBodyShape::Ring{ center, radius, count: count.max(3) })
+    }
+
+    pub fn grid(origin: Vec2, columns: usize, rows: usize, spacing: f32) -> Self{
+        Self::new(SoftBodyShape::Grid{ origin, columns: columns.max(2), rows: rows.max(2), spacing })
+    }
+
+    pub fn with_particle_radius(mut self, radius: f32) -> Self{
+        self.particle_radius = Some(radius);
+        self
+    }
+
+    pub fn with_particle_mass(mut self, mass: f32) -> Self{
+        self.particle_mass = mass;
+        self
+    }
+
+    pub fn with_springs(mut self, stiffness: f32, damping: f32) -> Self{
+        self.stiffness = stiffness;
+        self.damping = damping;
+        self
+    }
+
+    //a ring without pressure or area stiffness gets spokes to a center particle instead, or it would just collapse
+    pub fn with_pressure(mut self, pressure: f32) -> Self{
+        self.pressure = pressure;
+        self
+    }
+
+    pub fn with_area_stiffness(mut self, area_stiffness: f32) -> Self{
+        self.area_stiffness = area_stiffness;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self{
+        self.color = color;
+        self
+    }
+
+    fn has_pressure(&self) -> bool{
+        self.pressure != 0.0 || self.area_stiffness != 0.0
+    }
+
+    //(positions, hull indices, spring index pairs)
+    fn layout(&self) -> (Vec<Vec2>, Vec<usize>, Vec<(usize, usize)>){
+        match self.shape{
+            SoftBodyShape::Ring{ center, radius, count } => {
+                let mut positions: Vec<Vec2> = (0..count).map(|i| {
+                    let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+                    center + Vec2::new(angle.cos(), angle.sin()) * radius
+                }).collect();
+                let hull = (0..count).collect();
+                let mut links = Vec::new();
+                for i in 0..count{
+                    links.push((i, (i + 1) % count));
+                    //skipping one neighbour resists bending
+                    links.push((i, (i + 2) % count));
+                }
+                if !self.has_pressure(){
+                    positions.push(center);
+                    links.extend((0..count).map(|i| (i, count)));
+                }
+                (positions, hull, links)
+            },
+            SoftBodyShape::Grid{ origin, columns, rows, spacing } => {
+                let index = |column: usize, row: usize| row * columns + column;
+                let mut positions = Vec::with_capacity(columns * rows);
+                for row in 0..rows{
+                    for column in 0..columns{
+                        positions.push(origin + Vec2::new(column as f32, row as f32) * spacing);
+                    }
+                }
+                let mut links = Vec::new();
+                for row in 0..rows{
+                    for column in 0..columns{
+                        if column + 1 < columns{
+                            links.push((index(column, row), index(column + 1, row)));
+                        }
+                        if row + 1 < rows{
+                            links.push((index(column, row), index(column, row + 1)));
+                        }
+                        //diagonals keep the cells from shearing flat
+                        if column + 1 < columns && row + 1 < rows{
+                            links.push((index(column, row), index(column + 1, row + 1)));
+                            links.push((index(column + 1, row), index(column, row + 1)));
+                        }
+                    }
+                }
+                let mut hull = Vec::new();
+                hull.extend((0..columns).map(|column| index(column, 0)));
+                hull.extend((1..rows).map(|row| index(columns - 1, row)));
+                hull.extend((0..(columns - 1)).rev().map(|column| index(column, rows - 1)));
+                hull.extend((1..(rows - 1)).rev().map(|row| index(0, row)));
+                (positions, hull, links)
+            },
+        }
+    }
+
+    fn default_radius(&self) -> f32{
+        match self.shape{
+            SoftBodyShape::Ring{ radius, count, .. } => radius * (std::f32::consts::PI / count as f32).sin() * 0.9,
+            SoftBodyShape::Grid{ spacing, .. } => spacing * 0.45,
+        }
+    }
+
+    pub fn build(&self, world: &mut World) -> SoftBody{
+        let (positions, hull, links) = self.layout();
+        let radius = self.particle_radius.unwrap_or_else(|| self.default_radius());
+        //every spring steps as if it were the only one moving its ends, so the hub of a spoked ring
+        //pulled by all the spokes at once would be overshot. it weighs as much as the rim instead
+        let hub = match self.shape{
+            SoftBodyShape::Ring{ count, .. } if !self.has_pressure() => Some(count),
+            _ => None,
+        };
+        let particles: Vec<BodyHandle> = positions.iter().enumerate().map(|(i, position)| {
+            let mut body = Body::new(Shape::circle(radius), *position);
+            let mass = match hub{
+                Some(hub) if hub == i => self.particle_mass * hub as f32,
+                _ => self.particle_mass,
+            };
+            body.set_density(mass / body.shape.area());
+            //spinning particles mean nothing for the blob and would keep it from ever sleeping
+            body.set_fixed_rotation(true);
+            body.color = self.color;
+            world.add_body(body)
+        }).collect();
+        for (i, j) in links{
+            let rest_length = (positions[j] - positions[i]).length();
+            let spring = Spring::new(Anchor::body(particles[i]), Anchor::body(particles[j]), rest_length, self.stiffness)
+                .with_damping(self.damping);
+            world.add_spring(spring);
+        }
+
+        let hull: Vec<BodyHandle> = hull.into_iter().map(|i| particles[i]).collect();
+        let hull_positions: Vec<Vec2> = hull.iter().map(|handle| world.body(*handle).unwrap().position).collect();
+        let rest_area = shape::signed_area(&hull_positions);
+        if self.has_pressure(){
+            world.add_force_generator(Box::new(HullPressure{
+                hull: hull.clone(),
+                rest_area,
+                pressure: self.pressure,
+                area_stiffness: self.area_stiffness,
+            }));
+        }
+        SoftBody{
+            particles,
+            hull,
+            rest_area,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn run(world: &mut World, steps: usize){
+        for _ in 0..steps{
+            world.step(DT);
+        }
+    }
+
+    #[test]
+    fn grids_are_wired_up_with_a_counter_clockwise_hull(){
+        let mut world = World::new(Vec2::zero());
+        let soft = SoftBodyBuilder::grid(Vec2::zero(), 4, 3, 1.0).build(&mut world);
+        assert_eq!(soft.particles.len(), 12);
+        assert_eq!(soft.hull.len(), 10);
+        //3 * 3 across, 4 * 2 up and 2 diagonals in each of the 3 * 2 cells
+        assert_eq!(world.springs().len(), 9 + 8 + 12);
+        assert!((soft.rest_area - 6.0).abs() < 1e-4);
+        assert!((soft.area(&world) - soft.rest_area).abs() < 1e-4);
+        let triangles = soft.hull_triangles(&world);
+        assert_eq!(triangles.len(), soft.hull.len());
+        let covered: f32 = triangles.iter().map(|t| shape::signed_area(t)).sum();
+        assert!((covered - soft.rest_area).abs() < 1e-3);
+    }
+
+    #[test]
+    fn pressurised_rings_keep_their_area_on_the_ground(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        world.add_body(Body::new_static(Shape::rect(20.0, 1.0), Vec2::new(0.0, -0.5)));
+        let soft = SoftBodyBuilder::ring(Vec2::new(0.0, 2.0), 1.0, 16)
+            .with_pressure(5.0)
+            .with_area_stiffness(50.0)
+            .build(&mut world);
+        //no spokes when there is pressure
+        assert_eq!(soft.particles.len(), 16);
+        run(&mut world, 300);
+        let area = soft.area(&world);
+        assert!((area / soft.rest_area - 1.0).abs() < 0.3, "{area} vs {}", soft.rest_area);
+        let center = soft.center(&world);
+        assert!(center.y > 0.5 && center.y < 1.5, "{center:?}");
+    }
+
+    #[test]
+    fn rings_without_pressure_get_spokes(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        world.add_body(Body::new_static(Shape::rect(20.0, 1.0), Vec2::new(0.0, -0.5)));
+        let soft = SoftBodyBuilder::ring(Vec2::new(0.0, 2.0), 1.0, 12).build(&mut world);
+        assert_eq!(soft.particles.len(), 13);
+        assert_eq!(world.springs().len(), 36);
+        run(&mut world, 300);
+        assert!(soft.area(&world) > 0.7 * soft.rest_area);
+    }
+
+    #[test]
+    fn grids_hold_their_shape_when_dropped(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        world.add_body(Body::new_static(Shape::rect(20.0, 1.0), Vec2::new(0.0, -0.5)));
+        let soft = SoftBodyBuilder::grid(Vec2::new(-1.5, 3.0), 6, 6, 0.6).build(&mut world);
+        run(&mut world, 300);
+        let area = soft.area(&world);
+        assert!((area / soft.rest_area - 1.0).abs() < 0.2, "{area} vs {}", soft.rest_area);
+        assert!(soft.center(&world).y < 3.0);
+    }
+
+    #[test]
+    fn pressure_goes_away_with_a_hull_particle(){
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        let soft = SoftBodyBuilder::ring(Vec2::zero(), 1.0, 8).with_pressure(10.0).build(&mut world);
+        world.remove_body(soft.hull[0]);
+        //takes over the removed particle's slot, far away from the ring
+        let stranger = world.add_body(Body::new(Shape::circle(0.2), Vec2::new(50.0, 50.0)));
+        assert_eq!(stranger, soft.hull[0]);
+        run(&mut world, 10);
+        assert_eq!(world.body(stranger).unwrap().velocity, Vec2::zero());
+        assert_eq!(world.potential_energy(), 0.0);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::body::{self, Body};
+use crate::collision::Manifold;
+use crate::math::{self, Vec2};
+use crate::restitution::{Impact, RestitutionHook, RestitutionModel};
+
+//penetration we let slide so contacts don't flicker on and off
+const LINEAR_SLOP: f32 = 0.005;
+const CORRECTION_PERCENT: f32 = 0.4;
+
+fn tangent(normal: Vec2) -> Vec2{
+    Vec2::new(normal.y, -normal.x)
+}
+
+pub fn prepare(manifold: &mut Manifold, bodies: &[Option<Body>], model: &RestitutionModel, hook: Option<&RestitutionHook>){
+    let a = bodies[manifold.a.0].as_ref().unwrap();
+    let b = bodies[manifold.b.0].as_ref().unwrap();
+    let normal = manifold.normal;
+    let tangent = tangent(normal);
+    let inv_mass_sum = a.inv_mass() + b.inv_mass();
+
+    for point in manifold.points.iter_mut(){
+        point.ra = point.point - a.position;
+        point.rb = point.point - (b.position + manifold.offset);
+
+        let rna = point.ra.cross(normal);
+        let rnb = point.rb.cross(normal);
+        let k_normal = inv_mass_sum + a.inv_inertia() * rna * rna + b.inv_inertia() * rnb * rnb;
+        point.normal_mass = if k_normal > 0.0 { 1.0 / k_normal } else { 0.0 };
+
+        let rta = point.ra.cross(tangent);
+        let rtb = point.rb.cross(tangent);
+        let k_tangent = inv_mass_sum + a.inv_inertia() * rta * rta + b.inv_inertia() * rtb * rtb;
+        point.tangent_mass = if k_tangent > 0.0 { 1.0 / k_tangent } else { 0.0 };
+
+        let vn = relative_velocity(a, b, point.ra, point.rb).dot(normal);
+        point.velocity_bias = 0.0;
+        if vn < 0.0{
+            let speed = -vn;
+            let restitution = hook.and_then(|hook| hook(&Impact{
+                a: manifold.a,
+                body_a: a,
+                b: manifold.b,
+                body_b: b,
+                point: point.point,
+                normal,
+                speed,
+                restitution: manifold.restitution,
+            })).unwrap_or_else(|| model.coefficient(manifold.restitution, speed));
+            point.velocity_bias = restitution * speed;
+        }
+    }
+}
+
+//applies the impulses carried over from the last step, stacks need this to stay up
+pub fn warm_start(manifold: &Manifold, bodies: &mut [Option<Body>]){
+    let (a, b) = body::pair_mut(bodies, manifold.a.0, manifold.b.0);
+    let tangent = tangent(manifold.normal);
+    for point in manifold.points.iter(){
+        let impulse = manifold.normal * point.normal_impulse + tangent * point.tangent_impulse;
+        apply_impulse(a, b, point.ra, point.rb, impulse);
+    }
+}
+
+fn apply_impulse(a: &mut Body, b: &mut Body, ra: Vec2, rb: Vec2, impulse: Vec2){
+    a.velocity -= impulse * a.inv_mass();
+    a.angular_velocity -= a.inv_inertia() * ra.cross(impulse);
+    b.velocity += impulse * b.inv_mass();
+    b.angular_velocity += b.inv_inertia() * rb.cross(impulse);
+}
+
+fn relative_velocity(a: &Body, b: &Body, ra: Vec2, rb: Vec2) -> Vec2{
+    b.velocity + math::cross_sv(b.angular_velocity, rb) - a.velocity - math::cross_sv(a.angular_velocity, ra)
+}
+
+pub fn solve_velocity(manifold: &mut Manifold, bodies: &mut [Option<Body>]){
+    let (a, b) = body::pair_mut(bodies, manifold.a.0, manifold.b.0);
+    let normal = manifold.normal;
+    let tangent = tangent(normal);
+
+    for point in manifold.points.iter_mut(){
+        //friction
+        let vt = relative_velocity(a, b, point.ra, point.rb).dot(tangent);
+        let old = point.tangent_impulse;
+        let wanted = old - point.tangent_mass * vt;
+        //holds if static friction is enough, otherwise it slides against the dynamic friction
+        let max_static = manifold.static_friction * point.normal_impulse;
+        let max_friction = if wanted.abs() <= max_static { max_static } else { manifold.friction * point.normal_impulse };
+        point.tangent_impulse = wanted.clamp(-max_friction, max_friction);
+        apply_impulse(a, b, point.ra, point.rb, tangent * (point.tangent_impulse - old));
+
+        //normal
+        let vn = relative_velocity(a, b, point.ra, point.rb).dot(normal);
+        let old = point.normal_impulse;
+        point.normal_impulse = (old - point.normal_mass * (vn - point.velocity_bias)).max(0.0);
+        apply_impulse(a, b, point.ra, point.rb, normal * (point.normal_impulse - old));
+    }
+}
+
+//pushes the bodies apart directly so penetration doesn't build up over time
+pub fn correct_positions(manifold: &Manifold, bodies: &mut [Option<Body>]){
+    let (a, b) = body::pair_mut(bodies, manifold.a.0, manifold.b.0);
+    let inv_mass_sum = a.inv_mass() + b.inv_mass();
+    if inv_mass_sum <= 0.0{
+        return;
+    }
+    let correction = (manifold.max_penetration() - LINEAR_SLOP).max(0.0) * CORRECTION_PERCENT / inv_mass_sum;
+    a.position -= manifold.normal * (correction * a.inv_mass());
+    b.position += manifold.normal * (correction * b.inv_mass());
+}
@@ -0,0 +1,516 @@
+use std::f32::consts::PI;
+
+use crate::body::Body;
+use crate::broadphase::SpatialGrid;
+use crate::math::Vec2;
+use crate::shape::{Aabb, Shape};
+
+//smoothed particle hydrodynamics. the fluid particles are not bodies, they live in their own list and
+//see rigid bodies through sample points on the body outlines (boundary particles). the boundary samples
+//count towards the fluid density and take the pressure the fluid pushes on them back to their body
+
+//2d kernels, all of them are 0 from the smoothing length on
+fn poly6(r_sq: f32, h: f32) -> f32{
+    let d = h * h - r_sq;
+    if d <= 0.0 { 0.0 } else { 4.0 / (PI * h.powi(8)) * d * d * d }
+}
+
+//magnitude of the spiky kernel's gradient, points from the neighbour to the particle
+fn spiky_gradient(r: f32, h: f32) -> f32{
+    if r >= h { 0.0 } else { -30.0 / (PI * h.powi(5)) * (h - r) * (h - r) }
+}
+
+fn viscosity_laplacian(r: f32, h: f32) -> f32{
+    if r >= h { 0.0 } else { 40.0 / (PI * h.powi(5)) * (h - r) }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FluidParticle{
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub density: f32,
+    pub pressure: f32,
+    force: Vec2,
+}
+
+//index pairs of particles, or of a particle and a boundary sample
+type Pairs = Vec<(usize, usize)>;
+
+#[derive(Clone, Copy, Debug)]
+struct BoundaryParticle{
+    body: usize,
+    position: Vec2,
+    velocity: Vec2,
+    //how much fluid volume the sample stands in for, samples that crowd together count less each
+    volume: f32,
+}
+
+//what the renderer needs to draw a particle with the instanced circle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FluidInstance{
+    pub position: Vec2,
+    pub radius: f32,
+    pub color: [f32; 3],
+}
+
+pub struct Fluid{
+    pub particles: Vec<FluidParticle>,
+    //distance between particles at rest, the smoothing length is twice that
+    spacing: f32,
+    particle_mass: f32,
+    rest_density: f32,
+    //pressure per unit of density above the rest density. the speed of sound is about its square root
+    //and the substeps have to keep up with it
+    pub stiffness: f32,
+    pub viscosity: f32,
+    //viscosity against bodies, 0 lets the fluid slide along walls freely
+    pub boundary_viscosity: f32,
+    pub substeps: usize,
+    //bodies whose filter category shares a bit with this act as boundaries, sensors never do
+    pub categories: u32,
+    pub color: [f32; 3],
+    grid: SpatialGrid,
+    boundary: Vec<BoundaryParticle>,
+}
+
+impl Fluid{
+    pub fn new(spacing: f32) -> Self{
+        assert!(spacing > 0.0, "particle spacing has to be positive");
+        let mut fluid = Self{
+            particles: Vec::new(),
+            spacing,
+            particle_mass: 0.0,
+            rest_density: 1.0,
+            stiffness: 1000.0,
+            viscosity: 0.05,
+            boundary_viscosity: 0.02,
+            substeps: 8,
+            categories: u32::MAX,
+            color: [0.2, 0.4, 1.0],
+            grid: SpatialGrid::new(spacing * 2.0),
+            boundary: Vec::new(),
+        };
+        fluid.update_mass();
+        fluid
+    }
+
+    //same density as the bodies' default, so a body floats when it is lighter than that
+    pub fn with_rest_density(mut self, rest_density: f32) -> Self{
+        self.rest_density = rest_density;
+        self.update_mass();
+        self
+    }
+
+    pub fn with_stiffness(mut self, stiffness: f32) -> Self{
+        self.stiffness = stiffness;
+        self
+    }
+
+    pub fn with_viscosity(mut self, viscosity: f32, boundary_viscosity: f32) -> Self{
+        self.viscosity = viscosity;
+        self.boundary_viscosity = boundary_viscosity;
+        self
+    }
+
+    pub fn with_substeps(mut self, substeps: usize) -> Self{
+        self.substeps = substeps.max(1);
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self{
+        self.color = color;
+        self
+    }
+
+    pub fn smoothing_length(&self) -> f32{
+        self.spacing * 2.0
+    }
+
+    pub fn spacing(&self) -> f32{
+        self.spacing
+    }
+
+    pub fn particle_mass(&self) -> f32{
+        self.particle_mass
+    }
+
+    pub fn rest_density(&self) -> f32{
+        self.rest_density
+    }
+
+    //picks the mass so a square lattice at the spacing sums up to exactly the rest density
+    fn update_mass(&mut self){
+        let h = self.smoothing_length();
+        let mut sum = 0.0;
+        for x in -2..=2{
+            for y in -2..=2{
+                sum += poly6((Vec2::new(x as f32, y as f32) * self.spacing).length_squared(), h);
+            }
+        }
+        self.particle_mass = self.rest_density / sum;
+    }
+
+    pub fn add_particle(&mut self, position: Vec2, velocity: Vec2){
+        self.particles.push(FluidParticle{
+            position,
+            velocity,
+            density: self.rest_density,
+            pressure: 0.0,
+            force: Vec2::zero(),
+        });
+    }
+
+    //fills the box with particles at the rest spacing. keep it a spacing away from walls, the boundary
+    //samples already stand in for the fluid right against them and the block would start out squeezed
+    pub fn add_block(&mut self, min: Vec2, max: Vec2){
+        let columns = ((max.x - min.x) / self.spacing).floor() as usize;
+        let rows = ((max.y - min.y) / self.spacing).floor() as usize;
+        for row in 0..rows{
+            for column in 0..columns{
+                let offset = Vec2::new(column as f32 + 0.5, row as f32 + 0.5) * self.spacing;
+                self.add_particle(min + offset, Vec2::zero());
+            }
+        }
+    }
+
+    pub fn add_circle(&mut self, center: Vec2, radius: f32){
+        let steps = (radius / self.spacing).ceil() as i32;
+        for x in -steps..=steps{
+            for y in -steps..=steps{
+                let offset = Vec2::new(x as f32, y as f32) * self.spacing;
+                if offset.length_squared() <= radius * radius{
+                    self.add_particle(center + offset, Vec2::zero());
+                }
+            }
+        }
+    }
+
+    //drops particles that left the box, anything that escapes the containers would fall forever
+    pub fn retain_inside(&mut self, bounds: &Aabb){
+        self.particles.retain(|particle| bounds.contains_point(particle.position));
+    }
+
+    pub fn bounds(&self) -> Option<Aabb>{
+        let first = self.particles.first()?.position;
+        Some(self.particles.iter().fold(Aabb::new(first, first), |aabb, particle| {
+            aabb.union(&Aabb::new(particle.position, particle.position))
+        }))
+    }
+
+    pub fn kinetic_energy(&self) -> f32{
+        self.particles.iter().map(|particle| 0.5 * self.particle_mass * particle.velocity.length_squared()).sum()
+    }
+
+    pub fn momentum(&self) -> Vec2{
+        self.particles.iter().fold(Vec2::zero(), |sum, particle| sum + particle.velocity * self.particle_mass)
+    }
+
+    pub fn angular_momentum(&self, about: Vec2) -> f32{
+        self.particles.iter().map(|particle| (particle.position - about).cross(particle.velocity * self.particle_mass)).sum()
+    }
+
+    pub fn instances(&self) -> Vec<FluidInstance>{
+        self.particles.iter().map(|particle| FluidInstance{
+            position: particle.position,
+            radius: self.spacing * 0.5,
+            color: self.color,
+        }).collect()
+    }
+
+    //smoothed density at a point relative to the rest density, for a metaball pass. the surface of the
+    //fluid is around 0.5. goes over every particle, the neighbour grid is out of date as soon as
+    //particles are added or dropped between steps
+    pub fn field_at(&self, point: Vec2) -> f32{
+        let h = self.smoothing_length();
+        let density: f32 = self.particles.iter()
+            .map(|particle| self.particle_mass * poly6((particle.position - point).length_squared(), h))
+            .sum();
+        density / self.rest_density
+    }
+
+    fn takes_part(&self, body: &Body) -> bool{
+        !body.is_sensor && body.filter.category & self.categories != 0
+    }
+
+    //samples every body outline near the fluid, spaced like the particles
+    fn sample_boundary(&mut self, bodies: &[Option<Body>]){
+        self.boundary.clear();
+        let bounds = match self.bounds(){
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let margin = Vec2::new(1.0, 1.0) * self.smoothing_length() * 2.0;
+        let near = Aabb::new(bounds.min - margin, bounds.max + margin);
+        for (index, body) in bodies.iter().enumerate(){
+            let body = match body{
+                Some(body) if self.takes_part(body) && body.aabb().overlaps(&near) => body,
+                _ => continue,
+            };
+            let points = match &body.shape{
+                Shape::Circle{ radius } => {
+                    let count = ((2.0 * PI * radius / self.spacing).ceil() as usize).max(3);
+                    (0..count).map(|i| {
+                        let angle = i as f32 / count as f32 * 2.0 * PI;
+                        body.position + Vec2::new(angle.cos(), angle.sin()) * *radius
+                    }).collect()
+                },
+                Shape::Polygon{ .. } => {
+                    let vertices = body.shape.world_vertices(body.position, body.angle);
+                    let mut points = Vec::new();
+                    for i in 0..vertices.len(){
+                        let from = vertices[i];
+                        let edge = vertices[(i + 1) % vertices.len()] - from;
+                        let count = ((edge.length() / self.spacing).ceil() as usize).max(1);
+                        points.extend((0..count).map(|k| from + edge * (k as f32 / count as f32)));
+                    }
+                    points
+                },
+            };
+            for point in points.into_iter().filter(|point| near.contains_point(*point)){
+                self.boundary.push(BoundaryParticle{
+                    body: index,
+                    position: point,
+                    velocity: body.velocity_at(point),
+                    volume: 0.0,
+                });
+            }
+        }
+
+        //volume is one over the kernel sum of the samples around, a single layer of samples then
+        //pushes back about as hard as a solid wall full of fluid would
+        let h = self.smoothing_length();
+        let mut grid = SpatialGrid::new(h);
+        let half = Vec2::new(h, h) * 0.5;
+        for (i, sample) in self.boundary.iter().enumerate(){
+            grid.insert(i, Aabb::new(sample.position - half, sample.position + half));
+        }
+        let mut sums = vec![poly6(0.0, h); self.boundary.len()];
+        for (i, j) in grid.pairs(){
+            let w = poly6((self.boundary[i].position - self.boundary[j].position).length_squared(), h);
+            sums[i] += w;
+            sums[j] += w;
+        }
+        for (sample, sum) in self.boundary.iter_mut().zip(sums){
+            sample.volume = 1.0 / sum;
+        }
+    }
+
+    //fluid pairs as (i, j) and fluid to boundary pairs as (i, sample), both within the smoothing length
+    fn neighbours(&mut self) -> (Pairs, Pairs){
+        let h = self.smoothing_length();
+        let count = self.particles.len();
+        let half = Vec2::new(h, h) * 0.5;
+        self.grid.clear();
+        for (i, particle) in self.particles.iter().enumerate(){
+            self.grid.insert(i, Aabb::new(particle.position - half, particle.position + half));
+        }
+        for (b, sample) in self.boundary.iter().enumerate(){
+            self.grid.insert(count + b, Aabb::new(sample.position - half, sample.position + half));
+        }
+        let mut fluid = Vec::new();
+        let mut boundary = Vec::new();
+        for (i, j) in self.grid.pairs(){
+            if i >= count{
+                continue;
+            }
+            let other = if j < count { self.particles[j].position } else { self.boundary[j - count].position };
+            if (other - self.particles[i].position).length_squared() >= h * h{
+                continue;
+            }
+            if j < count{
+                fluid.push((i, j));
+            } else{
+                boundary.push((i, j - count));
+            }
+        }
+        (fluid, boundary)
+    }
+
+    //advances the fluid by dt and pushes the bodies it presses on. bodies stay put during the substeps,
+    //they get the average of the substep forces. sleeping bodies next to fluid moving faster than
+    //wake_speed are woken up
+    pub fn step(&mut self, bodies: &mut [Option<Body>], gravity: Vec2, wake_speed: f32, dt: f32){
+        if self.particles.is_empty() || dt <= 0.0{
+            return;
+        }
+        self.sample_boundary(bodies);
+        let substeps = self.substeps.max(1);
+        let h = dt / substeps as f32;
+        for _ in 0..substeps{
+            self.substep(bodies, gravity, wake_speed, h, 1.0 / substeps as f32);
+        }
+    }
+
+    fn substep(&mut self, bodies: &mut [Option<Body>], gravity: Vec2, wake_speed: f32, dt: f32, share: f32){
+        let h = self.smoothing_length();
+        let mass = self.particle_mass;
+        let (fluid, boundary) = self.neighbours();
+
+        for particle in self.particles.iter_mut(){
+            particle.density = mass * poly6(0.0, h);
+            particle.force = Vec2::zero();
+        }
+        for &(i, j) in fluid.iter(){
+            let w = mass * poly6((self.particles[i].position - self.particles[j].position).length_squared(), h);
+            self.particles[i].density += w;
+            self.particles[j].density += w;
+        }
+        for &(i, b) in boundary.iter(){
+            let sample = &self.boundary[b];
+            self.particles[i].density += self.rest_density * sample.volume * poly6((self.particles[i].position - sample.position).length_squared(), h);
+        }
+        for particle in self.particles.iter_mut(){
+            //no negative pressure, it would pull the free surface into clumps
+            particle.pressure = (self.stiffness * (particle.density - self.rest_density)).max(0.0);
+        }
+
+        for &(i, j) in fluid.iter(){
+            let (a, b) = (&self.particles[i], &self.particles[j]);
+            let offset = a.position - b.position;
+            let r = offset.length();
+            if r < f32::EPSILON{
+                continue;
+            }
+            let direction = offset / r;
+            let pressure = -mass * mass * (a.pressure / (a.density * a.density) + b.pressure / (b.density * b.density)) * spiky_gradient(r, h);
+            let viscosity = self.viscosity * mass * mass / (a.density * b.density) * viscosity_laplacian(r, h);
+            let force = direction * pressure + (b.velocity - a.velocity) * viscosity;
+            self.particles[i].force += force;
+            self.particles[j].force -= force;
+        }
+
+        for &(i, b) in boundary.iter(){
+            let sample = self.boundary[b];
+            let particle = &self.particles[i];
+            let offset = particle.position - sample.position;
+            let r = offset.length();
+            if r < f32::EPSILON{
+                continue;
+            }
+            //the sample mirrors the particle's own pressure and density
+            let sample_mass = self.rest_density * sample.volume;
+            let pressure = -mass * sample_mass * particle.pressure / (particle.density * particle.density) * spiky_gradient(r, h);
+            let viscosity = self.boundary_viscosity * mass * sample_mass / (particle.density * self.rest_density) * viscosity_laplacian(r, h);
+            let force = offset / r * pressure + (sample.velocity - particle.velocity) * viscosity;
+            let moving = particle.velocity.length() > wake_speed;
+            self.particles[i].force += force;
+            if let Some(body) = bodies[sample.body].as_mut(){
+                if !body.is_dynamic(){
+                    continue;
+                }
+                if !body.is_awake() && moving{
+                    body.set_awake(true);
+                }
+                if body.is_awake(){
+                    body.apply_force_at(-force * share, sample.position);
+                }
+            }
+        }
+
+        //bodies each particle has boundary samples of, to catch particles that made it through the samples
+        let mut near: Vec<Vec<usize>> = vec![Vec::new(); self.particles.len()];
+        for &(i, b) in boundary.iter(){
+            let body = self.boundary[b].body;
+            if !near[i].contains(&body){
+                near[i].push(body);
+            }
+        }
+        for (particle, near) in self.particles.iter_mut().zip(near){
+            let previous = particle.position;
+            particle.velocity += (gravity + particle.force / mass) * dt;
+            particle.position += particle.velocity * dt;
+            let inside = near.into_iter().filter_map(|index| bodies[index].as_ref())
+                .find(|body| body.shape.contains_point(body.position, body.angle, particle.position));
+            if let Some(body) = inside{
+                //put back where it was and carried along with the body
+                particle.position = previous;
+                particle.velocity = body.velocity_at(previous);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::world::World;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    //a box of static walls around [0, width] x [0, height]
+    fn tank(world: &mut World, width: f32, height: f32){
+        world.add_body(Body::new_static(Shape::rect(width + 2.0, 1.0), Vec2::new(width / 2.0, -0.5)));
+        world.add_body(Body::new_static(Shape::rect(1.0, height), Vec2::new(-0.5, height / 2.0)));
+        world.add_body(Body::new_static(Shape::rect(1.0, height), Vec2::new(width + 0.5, height / 2.0)));
+    }
+
+    #[test]
+    fn a_lattice_at_the_spacing_has_the_rest_density(){
+        let mut fluid = Fluid::new(0.1).with_rest_density(2.0);
+        fluid.add_block(Vec2::zero(), Vec2::new(1.0, 1.0));
+        assert_eq!(fluid.particles.len(), 100);
+        //a particle in the middle, with whole neighbourhoods all around
+        assert!((fluid.field_at(Vec2::new(0.45, 0.45)) - 1.0).abs() < 1e-3);
+        assert!(fluid.field_at(Vec2::new(0.0, 0.5)) < 0.8);
+        assert_eq!(fluid.field_at(Vec2::new(5.0, 5.0)), 0.0);
+        assert_eq!(fluid.instances().len(), 100);
+    }
+
+    #[test]
+    fn the_field_follows_particles_dropped_between_steps(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        let mut fluid = Fluid::new(0.1);
+        fluid.add_block(Vec2::zero(), Vec2::new(0.5, 0.5));
+        fluid.add_block(Vec2::new(3.0, 0.0), Vec2::new(3.5, 0.5));
+        let index = world.add_fluid(fluid);
+        world.step(DT);
+        let fluid = world.fluid_mut(index).unwrap();
+        fluid.retain_inside(&Aabb::new(Vec2::new(2.0, -5.0), Vec2::new(5.0, 5.0)));
+        assert_eq!(fluid.particles.len(), 25);
+        assert_eq!(fluid.field_at(Vec2::new(0.25, 0.2)), 0.0);
+        assert!(fluid.field_at(Vec2::new(3.25, 0.2)) > 0.5);
+    }
+
+    #[test]
+    fn fluid_settles_in_a_tank(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        tank(&mut world, 2.0, 3.0);
+        let mut fluid = Fluid::new(0.1);
+        fluid.add_block(Vec2::new(0.1, 0.1), Vec2::new(1.0, 1.5));
+        let count = fluid.particles.len();
+        let index = world.add_fluid(fluid);
+        for _ in 0..240{
+            world.step(DT);
+        }
+        let fluid = world.fluid(index).unwrap();
+        assert_eq!(fluid.particles.len(), count);
+        let bounds = fluid.bounds().unwrap();
+        assert!(bounds.min.x > -0.05 && bounds.max.x < 2.05 && bounds.min.y > -0.05, "{bounds:?}");
+        //spread over the floor, about as deep as the block had fluid for
+        let depth = count as f32 * 0.01 / 2.0;
+        assert!(bounds.max.y < depth * 1.5 + 0.1, "{bounds:?} vs {depth}");
+        assert!(fluid.kinetic_energy() / (count as f32) < 0.01);
+    }
+
+    #[test]
+    fn light_bodies_float_and_heavy_ones_sink(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        tank(&mut world, 3.0, 3.0);
+        let mut fluid = Fluid::new(0.1);
+        fluid.add_block(Vec2::new(0.1, 0.1), Vec2::new(2.9, 1.2));
+        world.add_fluid(fluid);
+        //put in right at the surface, a very light body dropped in from higher up gets thrown back out
+        let mut cork = Body::new(Shape::rect(0.4, 0.4), Vec2::new(0.8, 1.45));
+        cork.set_density(0.7);
+        let cork = world.add_body(cork);
+        let mut stone = Body::new(Shape::rect(0.4, 0.4), Vec2::new(2.2, 1.6));
+        stone.set_density(4.0);
+        let stone = world.add_body(stone);
+        for _ in 0..300{
+            world.step(DT);
+        }
+        let (cork, stone) = (world.body(cork).unwrap().position.y, world.body(stone).unwrap().position.y);
+        assert!(cork > 1.0, "{cork}");
+        assert!(stone < 0.4, "{stone}");
+    }
+}
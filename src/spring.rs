@@ -0,0 +1,176 @@
+use crate::body::{Body, BodyHandle};
+use crate::math::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor{
+    //point on a body, in the body's own frame so it turns with it
+    Body{ body: BodyHandle, local: Vec2 },
+    //fixed point in the world
+    World(Vec2),
+}
+
+impl Anchor{
+    //the center of the body
+    pub fn body(body: BodyHandle) -> Self{
+        Anchor::Body{ body, local: Vec2::zero() }
+    }
+
+    pub fn handle(&self) -> Option<BodyHandle>{
+        match self{
+            Anchor::Body{ body, .. } => Some(*body),
+            Anchor::World(_) => None,
+        }
+    }
+
+    //world position and velocity of the anchor, None if its body is gone
+    pub fn state(&self, bodies: &[Option<Body>]) -> Option<(Vec2, Vec2)>{
+        match self{
+            Anchor::Body{ body, local } => {
+                let body = bodies.get(body.0)?.as_ref()?;
+                let point = body.position + local.rotate(body.angle);
+                Some((point, body.velocity_at(point)))
+            },
+            Anchor::World(point) => Some((*point, Vec2::zero())),
+        }
+    }
+}
+
+//damped spring between two anchors, the world applies it every step and keeps
+//the bodies on both ends in the same island
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spring{
+    pub a: Anchor,
+    pub b: Anchor,
+    pub rest_length: f32,
+    pub stiffness: f32,
+    //resists the ends moving apart or together
+    pub damping: f32,
+}
+
+impl Spring{
+    pub fn new(a: Anchor, b: Anchor, rest_length: f32, stiffness: f32) -> Self{
+        Self{
+            a,
+            b,
+            rest_length,
+            stiffness,
+            damping: 0.0,
+        }
+    }
+
+    pub fn with_damping(mut self, damping: f32) -> Self{
+        self.damping = damping;
+        self
+    }
+
+    pub fn length(&self, bodies: &[Option<Body>]) -> Option<f32>{
+        let (a, _) = self.a.state(bodies)?;
+        let (b, _) = self.b.state(bodies)?;
+        Some((b - a).length())
+    }
+
+    pub fn energy(&self, bodies: &[Option<Body>]) -> f32{
+        let stretch = self.length(bodies).map_or(0.0, |length| length - self.rest_length);
+        0.5 * self.stiffness * stretch * stretch
+    }
+
+    //how hard the anchor is to move along the direction, 0 for world points and bodies the spring doesn't push
+    fn inverse_mass(anchor: &Anchor, bodies: &[Option<Body>], point: Vec2, direction: Vec2) -> f32{
+        match anchor.handle().and_then(|handle| bodies[handle.0].as_ref()){
+            Some(body) if body.is_dynamic() && body.is_awake() => {
+                let arm = (point - body.position).cross(direction);
+                body.inv_mass() + body.inv_inertia() * arm * arm
+            },
+            _ => 0.0,
+        }
+    }
+
+    //stepped implicitly along the spring so stiff springs on light bodies (ropes, soft bodies) don't blow up,
+    //the price is a bit of extra damping when the spring is very stiff for the step
+    pub fn apply(&self, bodies: &mut [Option<Body>], dt: f32){
+        let ((pa, va), (pb, vb)) = match (self.a.state(bodies), self.b.state(bodies)){
+            (Some(a), Some(b)) => (a, b),
+            _ => return,
+        };
+        let offset = pb - pa;
+        let length = offset.length();
+        if length < f32::EPSILON{
+            return;
+        }
+        let direction = offset / length;
+        let inv_mass = Self::inverse_mass(&self.a, bodies, pa, direction) + Self::inverse_mass(&self.b, bodies, pb, direction);
+        if inv_mass <= 0.0{
+            return;
+        }
+        let stretch = length - self.rest_length;
+        let speed = (vb - va).dot(direction);
+        let softness = self.damping + self.stiffness * dt;
+        //the force at the end of the step, where the speed has already changed by dt * inv_mass * force
+        let magnitude = (self.stiffness * stretch + softness * speed) / (1.0 + dt * softness * inv_mass);
+        let force = direction * magnitude;
+        for (anchor, point, sign) in [(self.a, pa, 1.0), (self.b, pb, -1.0)]{
+            if let Some(body) = anchor.handle().and_then(|handle| bodies[handle.0].as_mut()){
+                if body.is_dynamic() && body.is_awake(){
+                    body.apply_force_at(force * sign, point);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::shape::Shape;
+    use crate::world::World;
+
+    const DT: f32 = 1.0 / 120.0;
+
+    //a mass tied to a fixed point with no gravity, pulled out by one unit
+    fn oscillator(stiffness: f32, mass: f32) -> (World, BodyHandle){
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        let mut body = Body::new(Shape::circle(0.1), Vec2::new(3.0, 0.0));
+        body.set_density(mass / body.shape.area());
+        let handle = world.add_body(body);
+        world.add_spring(Spring::new(Anchor::World(Vec2::zero()), Anchor::body(handle), 2.0, stiffness));
+        (world, handle)
+    }
+
+    #[test]
+    fn soft_springs_oscillate_at_their_frequency(){
+        let (mut world, handle) = oscillator(10.0, 1.0);
+        //first time it comes back through the rest length, a quarter period
+        let mut crossing = None;
+        for step in 1..600{
+            world.step(DT);
+            if world.body(handle).unwrap().position.x < 2.0{
+                crossing = Some(step as f32 * DT);
+                break;
+            }
+        }
+        let quarter = std::f32::consts::FRAC_PI_2 / 10.0_f32.sqrt();
+        let crossing = crossing.unwrap();
+        assert!((crossing - quarter).abs() < 2.0 * DT, "{crossing} vs {quarter}");
+    }
+
+    #[test]
+    fn stiff_springs_on_light_bodies_stay_stable(){
+        let (mut world, handle) = oscillator(1.0e6, 0.01);
+        for _ in 0..600{
+            world.step(DT);
+            let x = world.body(handle).unwrap().position.x;
+            assert!(x.is_finite() && (x - 2.0).abs() <= 1.0 + 1e-3, "{x}");
+        }
+        //the implicit step damps what the step can't resolve, it settles instead of ringing
+        assert!((world.body(handle).unwrap().position.x - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn springs_go_away_with_their_bodies(){
+        let (mut world, handle) = oscillator(10.0, 1.0);
+        assert!((world.potential_energy() - 5.0).abs() < 1e-4);
+        world.remove_body(handle);
+        assert!(world.springs().is_empty());
+    }
+}
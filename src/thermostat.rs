@@ -0,0 +1,186 @@
+use crate::body::Body;
+use crate::force::ForceGenerator;
+use crate::math::Vec2;
+use crate::periodic::PeriodicBounds;
+use crate::random::Rng;
+use crate::shape::Aabb;
+
+//thermostats are force generators that touch velocities directly,
+//add them to the world like any other generator
+
+fn in_region(body: &Body, region: &Option<Aabb>) -> bool{
+    let inside = match region{
+        Some(region) => region.contains_point(body.position),
+        None => true,
+    };
+    body.is_dynamic() && body.is_awake() && inside
+}
+
+//2d kinetic temperature, every body has two translational degrees of freedom so KE = N k T
+pub fn kinetic_temperature<'a>(bodies: impl Iterator<Item = &'a Body>, boltzmann: f32) -> f32{
+    let mut energy = 0.0;
+    let mut count = 0;
+    for body in bodies.filter(|body| body.is_dynamic()){
+        energy += 0.5 * body.mass() * body.velocity.length_squared();
+        count += 1;
+    }
+    if count == 0{
+        return 0.0;
+    }
+    energy / (count as f32 * boltzmann)
+}
+
+fn region_temperature(bodies: &[Option<Body>], region: &Option<Aabb>, boltzmann: f32) -> f32{
+    kinetic_temperature(bodies.iter().flatten().filter(|body| in_region(body, region)), boltzmann)
+}
+
+fn scale_velocities(bodies: &mut [Option<Body>], region: &Option<Aabb>, scale: f32){
+    for body in bodies.iter_mut().flatten().filter(|body| in_region(body, region)){
+        body.velocity *= scale;
+    }
+}
+
+//sets the temperature exactly every step, crude but good for quickly getting to a temperature
+pub struct VelocityRescale{
+    pub temperature: f32,
+    pub boltzmann: f32,
+    //only bodies inside the region are measured and rescaled, None for the whole world
+    pub region: Option<Aabb>,
+}
+
+impl ForceGenerator for VelocityRescale{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, _dt: f32){
+        let current = region_temperature(bodies, &self.region, self.boltzmann);
+        if current > 0.0{
+            scale_velocities(bodies, &self.region, (self.temperature / current).sqrt());
+        }
+    }
+}
+
+//relaxes the temperature towards the target with time constant tau
+pub struct Berendsen{
+    pub temperature: f32,
+    pub tau: f32,
+    pub boltzmann: f32,
+    pub region: Option<Aabb>,
+}
+
+impl ForceGenerator for Berendsen{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, dt: f32){
+        let current = region_temperature(bodies, &self.region, self.boltzmann);
+        if current > 0.0{
+            let scale = (1.0 + dt / self.tau * (self.temperature / current - 1.0)).max(0.0).sqrt();
+            scale_velocities(bodies, &self.region, scale);
+        }
+    }
+}
+
+//bodies randomly "collide" with a heat bath and get a fresh maxwell-boltzmann velocity
+pub struct Andersen{
+    pub temperature: f32,
+    //bath collisions per body per second
+    pub collision_frequency: f32,
+    pub boltzmann: f32,
+    pub region: Option<Aabb>,
+    rng: Rng,
+}
+
+impl Andersen{
+    pub fn new(temperature: f32, collision_frequency: f32, seed: u64) -> Self{
+        Self{
+            temperature,
+            collision_frequency,
+            boltzmann: 1.0,
+            region: None,
+            rng: Rng::new(seed),
+        }
+    }
+
+    //a thin region along a wall with a very high bath frequency, anything that comes near leaves at the wall temperature
+    pub fn heated_wall(region: Aabb, temperature: f32, seed: u64) -> Self{
+        let mut thermostat = Self::new(temperature, 1000.0, seed);
+        thermostat.region = Some(region);
+        thermostat
+    }
+}
+
+impl ForceGenerator for Andersen{
+    fn apply(&mut self, bodies: &mut [Option<Body>], _periodic: &Option<PeriodicBounds>, dt: f32){
+        let probability = (self.collision_frequency * dt).min(1.0);
+        for body in bodies.iter_mut().flatten().filter(|body| in_region(body, &self.region)){
+            if self.rng.next_f32() >= probability || body.inv_mass() == 0.0{
+                continue;
+            }
+            let sigma = (self.boltzmann * self.temperature * body.inv_mass()).sqrt();
+            body.velocity = Vec2::new(self.rng.gaussian(), self.rng.gaussian()) * sigma;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::shape::Shape;
+    use crate::world::World;
+
+    fn gas(seed: u64) -> World{
+        let mut rng = Rng::new(seed);
+        let mut world = World::new(Vec2::zero());
+        world.sleep.enabled = false;
+        for i in 0..20{
+            for j in 0..20{
+                let mut body = Body::new(Shape::circle(0.1), Vec2::new(i as f32, j as f32));
+                body.velocity = Vec2::new(rng.gaussian(), rng.gaussian());
+                world.add_body(body);
+            }
+        }
+        world
+    }
+
+    fn run(world: &mut World, steps: usize){
+        for _ in 0..steps{
+            world.step(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn velocity_rescale_hits_the_target_in_one_step(){
+        let mut world = gas(1);
+        world.add_force_generator(Box::new(VelocityRescale{ temperature: 3.0, boltzmann: 1.0, region: None }));
+        run(&mut world, 1);
+        assert!((world.kinetic_temperature(1.0) - 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn berendsen_relaxes_towards_the_target(){
+        let mut world = gas(2);
+        let start = world.kinetic_temperature(1.0);
+        world.add_force_generator(Box::new(Berendsen{ temperature: 0.5, tau: 0.5, boltzmann: 1.0, region: None }));
+        run(&mut world, 15);
+        //the light particles start out cold, a quarter second in they are about half way
+        let early = world.kinetic_temperature(1.0);
+        assert!(early > start && early < 0.4);
+        run(&mut world, 600);
+        assert!((world.kinetic_temperature(1.0) - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn andersen_thermalizes_the_gas(){
+        let mut world = gas(3);
+        world.add_force_generator(Box::new(Andersen::new(2.0, 5.0, 9)));
+        run(&mut world, 600);
+        assert!((world.kinetic_temperature(1.0) - 2.0).abs() < 0.4);
+    }
+
+    #[test]
+    fn thermostat_only_touches_its_region(){
+        let mut world = gas(4);
+        let outside = world.bodies().find(|(_, body)| body.position.x > 10.0).unwrap().0;
+        let velocity = world.body(outside).unwrap().velocity;
+        let region = Some(Aabb::new(Vec2::new(-1.0, -1.0), Vec2::new(5.0, 20.0)));
+        world.add_force_generator(Box::new(VelocityRescale{ temperature: 0.0, boltzmann: 1.0, region }));
+        run(&mut world, 1);
+        assert_eq!(world.body(outside).unwrap().velocity, velocity);
+        assert!(world.bodies().filter(|(_, body)| body.position.x < 4.0).all(|(_, body)| body.velocity == Vec2::zero()));
+    }
+}
@@ -0,0 +1,1029 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::body::{Body, BodyHandle};
+use crate::broadphase::SpatialGrid;
+use crate::buoyancy::FluidZone;
+use crate::collision::{self, Manifold};
+use crate::events::{self, CollisionEvent, ContactListener};
+use crate::filter::PairFilter;
+use crate::force::ForceGenerator;
+use crate::fracture::{self, FractureEvent};
+use crate::island::{self, Island, SleepSettings};
+use crate::material::CombineRules;
+use crate::math::Vec2;
+use crate::merge::{self, CollisionOutcome, MergeEvent, OutcomeRule};
+use crate::path::PathFollower;
+use crate::periodic::{self, PeriodicBounds};
+use crate::query::{self, CastHit};
+use crate::random::Rng;
+use crate::restitution::{RestitutionHook, RestitutionModel};
+use crate::sensor::{SensorEvent, SensorTracker};
+use crate::shape::{Aabb, Shape};
+use crate::solver;
+use crate::sph::Fluid;
+use crate::spring::Spring;
+use crate::thermostat;
+
+//contact points closer than this to last step's points reuse their impulses
+const WARM_START_DISTANCE: f32 = 0.05;
+
+pub struct World{
+    bodies: Vec<Option<Body>>,
+    free_slots: Vec<usize>,
+    pub gravity: Vec2,
+    pub velocity_iterations: usize,
+    pub sleep: SleepSettings,
+    //how the two bodies' restitution and friction make the contact's
+    pub combine_rules: CombineRules,
+    pub restitution_model: RestitutionModel,
+    //wrap-around edges instead of an open world, see periodic.rs
+    pub periodic: Option<PeriodicBounds>,
+    broadphase: SpatialGrid,
+    manifolds: Vec<Manifold>,
+    islands: Vec<Island>,
+    pair_filter: Option<PairFilter>,
+    sensors: SensorTracker,
+    touching: HashSet<(BodyHandle, BodyHandle)>,
+    collision_events: Vec<CollisionEvent>,
+    //end events for bodies removed since the last dispatch, they go out with the next step's events
+    removed_contacts: Vec<CollisionEvent>,
+    listener: Option<Box<dyn ContactListener>>,
+    force_generators: Vec<Box<dyn ForceGenerator>>,
+    pub collision_outcome: CollisionOutcome,
+    outcome_rule: Option<OutcomeRule>,
+    restitution_hook: Option<RestitutionHook>,
+    merge_events: Vec<MergeEvent>,
+    path_followers: Vec<PathFollower>,
+    fracture_events: Vec<FractureEvent>,
+    springs: Vec<Spring>,
+    fluids: Vec<Fluid>,
+    fluid_zones: Vec<FluidZone>,
+    //where the voronoi seeds come from, seeded so runs repeat
+    fracture_rng: Rng,
+}
+
+impl World{
+    pub fn new(gravity: Vec2) -> Self{
+        Self{
+            bodies: Vec::new(),
+            free_slots: Vec::new(),
+            gravity,
+            velocity_iterations: 10,
+            sleep: SleepSettings::default(),
+            combine_rules: CombineRules::default(),
+            restitution_model: RestitutionModel::default(),
+            periodic: None,
+            broadphase: SpatialGrid::new(1.0),
+            manifolds: Vec::new(),
+            islands: Vec::new(),
+            pair_filter: None,
+            sensors: SensorTracker::new(),
+            touching: HashSet::new(),
+            collision_events: Vec::new(),
+            removed_contacts: Vec::new(),
+            listener: None,
+            force_generators: Vec::new(),
+            collision_outcome: CollisionOutcome::Bounce,
+            outcome_rule: None,
+            restitution_hook: None,
+            merge_events: Vec::new(),
+            path_followers: Vec::new(),
+            fracture_events: Vec::new(),
+            springs: Vec::new(),
+            fluids: Vec::new(),
+            fluid_zones: Vec::new(),
+            fracture_rng: Rng::new(0),
+        }
+    }
+
+    pub fn add_body(&mut self, body: Body) -> BodyHandle{
+        //queries should see the body before the next step
+        let aabb = body.aabb();
+        let handle = match self.free_slots.pop(){
+            Some(slot) => {
+                self.bodies[slot] = Some(body);
+                BodyHandle(slot)
+            },
+            None => {
+                self.bodies.push(Some(body));
+                BodyHandle(self.bodies.len() - 1)
+            },
+        };
+        self.broadphase.insert(handle.0, aabb);
+        handle
+    }
+
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<Body>{
+        let body = self.bodies.get_mut(handle.0)?.take()?;
+        self.free_slots.push(handle.0);
+        self.broadphase.remove(handle.0);
+        self.manifolds.retain(|m| m.a != handle && m.b != handle);
+        self.sensors.remove_body(handle);
+        self.path_followers.retain(|follower| follower.body != handle);
+        self.springs.retain(|spring| spring.a.handle() != Some(handle) && spring.b.handle() != Some(handle));
+        self.force_generators.retain_mut(|generator| generator.body_removed(handle));
+        let mut ended: Vec<(BodyHandle, BodyHandle)> = self.touching.iter().filter(|(a, b)| *a == handle || *b == handle).copied().collect();
+        ended.sort();
+        for (a, b) in ended{
+            self.touching.remove(&(a, b));
+            self.removed_contacts.push(CollisionEvent::End{ a, b });
+        }
+        Some(body)
+    }
+
+    pub fn body(&self, handle: BodyHandle) -> Option<&Body>{
+        self.bodies.get(handle.0)?.as_ref()
+    }
+
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut Body>{
+        self.bodies.get_mut(handle.0)?.as_mut()
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &Body)>{
+        self.bodies.iter().enumerate().filter_map(|(i, body)| body.as_ref().map(|body| (BodyHandle(i), body)))
+    }
+
+    pub fn bodies_mut(&mut self) -> impl Iterator<Item = (BodyHandle, &mut Body)>{
+        self.bodies.iter_mut().enumerate().filter_map(|(i, body)| body.as_mut().map(|body| (BodyHandle(i), body)))
+    }
+
+    pub fn body_count(&self) -> usize{
+        self.bodies.len() - self.free_slots.len()
+    }
+
+    pub fn contacts(&self) -> &[Manifold]{
+        &self.manifolds
+    }
+
+    pub fn islands(&self) -> &[Island]{
+        &self.islands
+    }
+
+    //begin/end contact events of the last step
+    pub fn collision_events(&self) -> &[CollisionEvent]{
+        &self.collision_events
+    }
+
+    pub fn set_contact_listener(&mut self, listener: Box<dyn ContactListener>){
+        self.listener = Some(listener);
+    }
+
+    pub fn take_contact_listener(&mut self) -> Option<Box<dyn ContactListener>>{
+        self.listener.take()
+    }
+
+    pub fn is_touching(&self, a: BodyHandle, b: BodyHandle) -> bool{
+        self.touching.contains(&(a.min(b), a.max(b)))
+    }
+
+    //enter/stay/exit events of the last step
+    pub fn sensor_events(&self) -> &[SensorEvent]{
+        self.sensors.events()
+    }
+
+    pub fn bodies_in_sensor(&self, sensor: BodyHandle) -> Vec<BodyHandle>{
+        let mut bodies: Vec<BodyHandle> = self.sensors.overlaps_of(sensor).collect();
+        bodies.sort();
+        bodies
+    }
+
+    pub fn add_force_generator(&mut self, generator: Box<dyn ForceGenerator>){
+        self.force_generators.push(generator);
+    }
+
+    pub fn clear_force_generators(&mut self){
+        self.force_generators.clear();
+    }
+
+    pub fn add_spring(&mut self, spring: Spring){
+        self.springs.push(spring);
+    }
+
+    //springs go away with the bodies they are attached to, so indices are not stable
+    pub fn springs(&self) -> &[Spring]{
+        &self.springs
+    }
+
+    pub fn springs_mut(&mut self) -> &mut Vec<Spring>{
+        &mut self.springs
+    }
+
+    //returns the index to look the fluid up with, fluids are never removed
+    pub fn add_fluid(&mut self, fluid: Fluid) -> usize{
+        self.fluids.push(fluid);
+        self.fluids.len() - 1
+    }
+
+    pub fn fluid(&self, index: usize) -> Option<&Fluid>{
+        self.fluids.get(index)
+    }
+
+    pub fn fluid_mut(&mut self, index: usize) -> Option<&mut Fluid>{
+        self.fluids.get_mut(index)
+    }
+
+    pub fn fluids(&self) -> &[Fluid]{
+        &self.fluids
+    }
+
+    //same as fluids, the index stays valid
+    pub fn add_fluid_zone(&mut self, zone: FluidZone) -> usize{
+        self.fluid_zones.push(zone);
+        self.fluid_zones.len() - 1
+    }
+
+    pub fn fluid_zone(&self, index: usize) -> Option<&FluidZone>{
+        self.fluid_zones.get(index)
+    }
+
+    pub fn fluid_zone_mut(&mut self, index: usize) -> Option<&mut FluidZone>{
+        self.fluid_zones.get_mut(index)
+    }
+
+    pub fn fluid_zones(&self) -> &[FluidZone]{
+        &self.fluid_zones
+    }
+
+    pub fn fracture_events(&self) -> &[FractureEvent]{
+        &self.fracture_events
+    }
+
+    //the body is put where the path says it is at the follower's time, otherwise the first step
+    //would give it whatever velocity closes the gap in one step and launch it
+    pub fn add_path_follower(&mut self, follower: PathFollower){
+        if let Some(body) = self.body_mut(follower.body){
+            body.position = follower.path.position_at(follower.time);
+            if let Some(angle) = follower.path.angle_at(follower.time){
+                body.angle = angle;
+            }
+        }
+        self.path_followers.push(follower);
+    }
+
+    pub fn path_followers_mut(&mut self) -> &mut Vec<PathFollower>{
+        &mut self.path_followers
+    }
+
+    //decides bounce or merge per pair instead of the world-wide collision_outcome
+    pub fn set_outcome_rule(&mut self, rule: OutcomeRule){
+        self.outcome_rule = Some(rule);
+    }
+
+    pub fn clear_outcome_rule(&mut self){
+        self.outcome_rule = None;
+    }
+
+    //overrides the restitution model for the contact points it returns Some for
+    pub fn set_restitution_hook(&mut self, hook: RestitutionHook){
+        self.restitution_hook = Some(hook);
+    }
+
+    pub fn clear_restitution_hook(&mut self){
+        self.restitution_hook = None;
+    }
+
+    pub fn merge_events(&self) -> &[MergeEvent]{
+        &self.merge_events
+    }
+
+    //custom rule on top of the category/mask/group filter, called for every broadphase pair
+    pub fn set_pair_filter(&mut self, filter: PairFilter){
+        self.pair_filter = Some(filter);
+    }
+
+    pub fn clear_pair_filter(&mut self){
+        self.pair_filter = None;
+    }
+
+    pub fn should_collide(&self, a: BodyHandle, b: BodyHandle) -> bool{
+        match (self.body(a), self.body(b)){
+            (Some(body_a), Some(body_b)) => self.filter_pair(a, body_a, b, body_b),
+            _ => false,
+        }
+    }
+
+    fn filter_pair(&self, a: BodyHandle, body_a: &Body, b: BodyHandle, body_b: &Body) -> bool{
+        if !body_a.is_dynamic() && !body_b.is_dynamic(){
+            return false;
+        }
+        if !body_a.filter.should_collide(&body_b.filter){
+            return false;
+        }
+        match &self.pair_filter{
+            Some(filter) => filter(a, body_a, b, body_b),
+            None => true,
+        }
+    }
+
+    pub fn set_broadphase_cell_size(&mut self, cell_size: f32){
+        self.broadphase.set_cell_size(cell_size);
+        self.rebuild_broadphase();
+    }
+
+    //NOTE: queries use the broadphase from the end of the last step,
+    //bodies moved by hand since then are found at their old place
+    pub fn raycast(&self, from: Vec2, to: Vec2, filter: impl Fn(BodyHandle, &Body) -> bool) -> Option<CastHit>{
+        self.raycast_all(from, to, filter).into_iter().next()
+    }
+
+    //every hit along the ray, closest first
+    pub fn raycast_all(&self, from: Vec2, to: Vec2, filter: impl Fn(BodyHandle, &Body) -> bool) -> Vec<CastHit>{
+        let bounds = Aabb::new(
+            Vec2::new(from.x.min(to.x), from.y.min(to.y)),
+            Vec2::new(from.x.max(to.x), from.y.max(to.y)),
+        );
+        let mut hits: Vec<CastHit> = self.candidates(&bounds, &filter).into_iter().filter_map(|(handle, body, offset)| {
+            let (fraction, normal) = query::raycast_shape(&body.shape, body.position + offset, body.angle, from, to)?;
+            Some(CastHit{
+                body: handle,
+                point: from + (to - from) * fraction,
+                normal,
+                fraction,
+            })
+        }).collect();
+        hits.sort_by(|a, b| a.fraction.total_cmp(&b.fraction));
+        //the nearest image of each body
+        let mut seen = HashSet::new();
+        hits.retain(|hit| seen.insert(hit.body));
+        hits
+    }
+
+    //sweeps the shape from `from` to `to` and returns the first body it would touch
+    pub fn shape_cast(&self, shape: &Shape, angle: f32, from: Vec2, to: Vec2, filter: impl Fn(BodyHandle, &Body) -> bool) -> Option<CastHit>{
+        let bounds = shape.aabb(from, angle).union(&shape.aabb(to, angle));
+        self.candidates(&bounds, &filter).into_iter().filter_map(|(handle, body, offset)| {
+            let (fraction, point, normal) = query::shape_cast(shape, angle, from, to, &body.shape, body.position + offset, body.angle)?;
+            Some(CastHit{
+                body: handle,
+                point,
+                normal,
+                fraction,
+            })
+        }).min_by(|a, b| a.fraction.total_cmp(&b.fraction))
+    }
+
+    //bodies whose shape contains the point, e.g. for mouse picking
+    pub fn query_point(&self, point: Vec2, filter: impl Fn(BodyHandle, &Body) -> bool) -> Vec<BodyHandle>{
+        let mut found: Vec<BodyHandle> = self.candidates(&Aabb::new(point, point), &filter).into_iter()
+            .filter(|(_, body, offset)| body.shape.contains_point(body.position + *offset, body.angle, point))
+            .map(|(handle, _, _)| handle)
+            .collect();
+        found.dedup();
+        found
+    }
+
+    //bodies whose bounding box overlaps the region
+    pub fn query_aabb(&self, aabb: &Aabb, filter: impl Fn(BodyHandle, &Body) -> bool) -> Vec<BodyHandle>{
+        let mut found: Vec<BodyHandle> = self.candidates(aabb, &filter).into_iter()
+            .map(|(handle, _, _)| handle)
+            .collect();
+        found.dedup();
+        found
+    }
+
+    //bodies actually overlapping the shape placed at position/angle
+    pub fn query_shape(&self, shape: &Shape, position: Vec2, angle: f32, filter: impl Fn(BodyHandle, &Body) -> bool) -> Vec<BodyHandle>{
+        let mut found: Vec<BodyHandle> = self.candidates(&shape.aabb(position, angle), &filter).into_iter()
+            .filter(|(_, body, offset)| collision::collide_shapes(shape, position, angle, &body.shape, body.position + *offset, body.angle).is_some())
+            .map(|(handle, _, _)| handle)
+            .collect();
+        found.dedup();
+        found
+    }
+
+    //bodies near the bounds that get through the filter, each with the shift that moves it to where
+    //the query sees it. in a periodic world that is every image of the body touching the query,
+    //so the exact tests agree with the broadphase across the edges. a body's shifts come one after the other
+    fn candidates(&self, bounds: &Aabb, filter: &impl Fn(BodyHandle, &Body) -> bool) -> Vec<(BodyHandle, &Body, Vec2)>{
+        let periodic = match &self.periodic{
+            Some(periodic) => periodic,
+            None => return self.broadphase.query(bounds).into_iter().filter_map(|i| {
+                let body = self.bodies.get(i)?.as_ref()?;
+                if filter(BodyHandle(i), body) { Some((BodyHandle(i), body, Vec2::zero())) } else { None }
+            }).collect(),
+        };
+        //a query hanging over an edge also looks on the other side
+        let mut query_shifts = vec![Vec2::zero()];
+        query_shifts.extend(periodic.image_offsets(bounds));
+        let mut indices: Vec<usize> = query_shifts.iter()
+            .flat_map(|shift| self.broadphase.query(&Aabb::new(bounds.min + *shift, bounds.max + *shift)))
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        let mut candidates = Vec::new();
+        for i in indices{
+            let body = match self.bodies.get(i).and_then(|body| body.as_ref()){
+                Some(body) if filter(BodyHandle(i), body) => body,
+                _ => continue,
+            };
+            let aabb = body.aabb();
+            let mut body_shifts = vec![Vec2::zero()];
+            body_shifts.extend(periodic.image_offsets(&aabb));
+            let mut offsets: Vec<Vec2> = Vec::new();
+            for body_shift in body_shifts.iter(){
+                for query_shift in query_shifts.iter(){
+                    let offset = *body_shift - *query_shift;
+                    if !offsets.contains(&offset) && Aabb::new(aabb.min + offset, aabb.max + offset).overlaps(bounds){
+                        offsets.push(offset);
+                    }
+                }
+            }
+            candidates.extend(offsets.into_iter().map(|offset| (BodyHandle(i), body, offset)));
+        }
+        candidates
+    }
+
+    fn rebuild_broadphase(&mut self){
+        self.broadphase.clear();
+        for (i, body) in self.bodies.iter().enumerate(){
+            if let Some(body) = body{
+                let aabb = body.aabb();
+                self.broadphase.insert(i, aabb);
+                //bodies hanging over a periodic edge are also entered on the other side
+                if let Some(periodic) = &self.periodic{
+                    for offset in periodic.image_offsets(&aabb){
+                        self.broadphase.insert(i, Aabb::new(aabb.min + offset, aabb.max + offset));
+                    }
+                }
+            }
+        }
+    }
+
+    //offset from one point to another, the shortest way around when the world is periodic
+    pub fn separation(&self, from: Vec2, to: Vec2) -> Vec2{
+        periodic::separation(&self.periodic, from, to)
+    }
+
+    //extra places to draw bodies that hang over a periodic edge
+    pub fn ghost_positions(&self) -> Vec<(BodyHandle, Vec2)>{
+        let periodic = match &self.periodic{
+            Some(periodic) => periodic,
+            None => return Vec::new(),
+        };
+        let mut ghosts = Vec::new();
+        for (handle, body) in self.bodies(){
+            for offset in periodic.image_offsets(&body.aabb()){
+                ghosts.push((handle, body.position + offset));
+            }
+        }
+        ghosts
+    }
+
+    fn wrap_positions(&mut self){
+        let periodic = match self.periodic{
+            Some(periodic) => periodic,
+            None => return,
+        };
+        for (_, body) in self.bodies_mut(){
+            if body.is_dynamic(){
+                body.position = periodic.wrap(body.position);
+            }
+        }
+    }
+
+    //wakes the body and its whole island (the island follows on the next step)
+    pub fn wake_body(&mut self, handle: BodyHandle){
+        if let Some(body) = self.body_mut(handle){
+            body.set_awake(true);
+        }
+    }
+
+    pub fn apply_impulse(&mut self, handle: BodyHandle, impulse: Vec2, point: Vec2){
+        if let Some(body) = self.body_mut(handle){
+            body.apply_impulse(impulse, point);
+        }
+    }
+
+    pub fn kinetic_temperature(&self, boltzmann: f32) -> f32{
+        thermostat::kinetic_temperature(self.bodies().map(|(_, body)| body), boltzmann)
+    }
+
+    //totals over the dynamic bodies and fluid particles, used by the conservation diagnostics
+    pub fn kinetic_energy(&self) -> f32{
+        let bodies: f32 = self.bodies().filter(|(_, body)| body.is_dynamic()).map(|(_, body)| body.kinetic_energy()).sum();
+        bodies + self.fluids.iter().map(|fluid| fluid.kinetic_energy()).sum::<f32>()
+    }
+
+    //world gravity plus whatever the force generators report, needs &mut because some of them keep neighbour lists
+    pub fn potential_energy(&mut self) -> f32{
+        let gravity = self.gravity;
+        let mut energy: f32 = self.bodies().filter(|(_, body)| body.is_dynamic())
+            .map(|(_, body)| -body.mass() * gravity.dot(body.position))
+            .sum();
+        for fluid in self.fluids.iter(){
+            energy -= fluid.particles.iter().map(|particle| fluid.particle_mass() * gravity.dot(particle.position)).sum::<f32>();
+        }
+        for generator in self.force_generators.iter_mut(){
+            energy += generator.potential_energy(&self.bodies, &self.periodic);
+        }
+        energy += self.springs.iter().map(|spring| spring.energy(&self.bodies)).sum::<f32>();
+        energy
+    }
+
+    pub fn linear_momentum(&self) -> Vec2{
+        let mut momentum = Vec2::zero();
+        for (_, body) in self.bodies().filter(|(_, body)| body.is_dynamic()){
+            momentum += body.velocity * body.mass();
+        }
+        for fluid in self.fluids.iter(){
+            momentum += fluid.momentum();
+        }
+        momentum
+    }
+
+    //orbital part around the given point plus the spin of every body
+    pub fn angular_momentum(&self, about: Vec2) -> f32{
+        self.bodies().filter(|(_, body)| body.is_dynamic())
+            .map(|(_, body)| (body.position - about).cross(body.velocity * body.mass()) + body.inertia() * body.angular_velocity)
+            .sum::<f32>() + self.fluids.iter().map(|fluid| fluid.angular_momentum(about)).sum::<f32>()
+    }
+
+    //numbers for the debug overlay
+    pub fn sleeping_body_count(&self) -> usize{
+        self.bodies().filter(|(_, body)| body.is_dynamic() && !body.is_awake()).count()
+    }
+
+    pub fn awake_island_count(&self) -> usize{
+        self.islands.iter().filter(|island| island.awake).count()
+    }
+
+    pub fn step(&mut self, dt: f32){
+        if dt <= 0.0{
+            return;
+        }
+        self.collision_events.clear();
+        self.collision_events.append(&mut self.removed_contacts);
+        self.merge_events.clear();
+        self.fracture_events.clear();
+        for generator in self.force_generators.iter_mut(){
+            generator.apply(&mut self.bodies, &self.periodic, dt);
+        }
+        for spring in self.springs.iter(){
+            spring.apply(&mut self.bodies, dt);
+        }
+        for fluid in self.fluids.iter_mut(){
+            fluid.step(&mut self.bodies, self.gravity, self.sleep.linear_tolerance, dt);
+        }
+        for zone in self.fluid_zones.iter(){
+            zone.apply(&mut self.bodies, self.gravity);
+        }
+        self.integrate_velocities(dt);
+        self.follow_paths(dt);
+        let mut begins = self.find_contacts();
+        self.resolve_merges(&mut begins);
+
+        self.islands = island::build_islands(&self.bodies, &self.manifolds, &self.springs);
+        island::wake_islands(&self.islands, &mut self.bodies);
+
+        self.pre_solve();
+        let closing_speeds: Vec<f32> = self.manifolds.iter().map(|m| {
+            events::closing_speed(m, self.bodies[m.a.0].as_ref().unwrap(), self.bodies[m.b.0].as_ref().unwrap())
+        }).collect();
+
+        for manifold in self.manifolds.iter_mut().filter(|m| m.enabled){
+            solver::prepare(manifold, &self.bodies, &self.restitution_model, self.restitution_hook.as_ref());
+        }
+        for manifold in self.manifolds.iter().filter(|m| m.enabled){
+            solver::warm_start(manifold, &mut self.bodies);
+        }
+        for _ in 0..self.velocity_iterations{
+            for manifold in self.manifolds.iter_mut().filter(|m| m.enabled){
+                solver::solve_velocity(manifold, &mut self.bodies);
+            }
+        }
+        self.post_solve(&begins, &closing_speeds);
+
+        self.integrate_positions(dt);
+        for manifold in self.manifolds.iter().filter(|m| m.enabled){
+            solver::correct_positions(manifold, &mut self.bodies);
+        }
+        self.wrap_positions();
+        self.break_bodies();
+
+        for (_, body) in self.bodies_mut(){
+            body.force = Vec2::zero();
+            body.torque = 0.0;
+        }
+        island::update_sleep(&mut self.islands, &mut self.bodies, &self.sleep, dt);
+        self.rebuild_broadphase();
+    }
+
+    fn integrate_velocities(&mut self, dt: f32){
+        let gravity = self.gravity;
+        for (_, body) in self.bodies_mut(){
+            if !body.is_dynamic() || !body.is_awake(){
+                continue;
+            }
+            body.velocity += (gravity + body.force * body.inv_mass()) * dt;
+            body.angular_velocity += body.torque * body.inv_inertia() * dt;
+        }
+    }
+
+    fn follow_paths(&mut self, dt: f32){
+        for follower in self.path_followers.iter_mut(){
+            if let Some(body) = self.bodies[follower.body.0].as_mut(){
+                let (velocity, angular_velocity) = follower.advance(body.position, body.angle, dt);
+                body.velocity = velocity;
+                body.angular_velocity = angular_velocity;
+            }
+        }
+    }
+
+    fn integrate_positions(&mut self, dt: f32){
+        for (_, body) in self.bodies_mut(){
+            //kinematic bodies never fall asleep
+            let moves = body.is_kinematic() || (body.is_dynamic() && body.is_awake());
+            if !moves{
+                continue;
+            }
+            body.position += body.velocity * dt;
+            body.angle += body.angular_velocity * dt;
+        }
+    }
+
+    fn outcome(&self, manifold: &Manifold) -> CollisionOutcome{
+        let a = self.bodies[manifold.a.0].as_ref().unwrap();
+        let b = self.bodies[manifold.b.0].as_ref().unwrap();
+        if !a.is_dynamic() || !b.is_dynamic(){
+            return CollisionOutcome::Bounce;
+        }
+        match &self.outcome_rule{
+            Some(rule) => rule(manifold.a, a, manifold.b, b),
+            None => self.collision_outcome,
+        }
+    }
+
+    //the heavier body keeps its handle, the lighter one is removed
+    fn resolve_merges(&mut self, begins: &mut HashSet<(BodyHandle, BodyHandle)>){
+        let merging: Vec<(BodyHandle, BodyHandle, Vec2)> = self.manifolds.iter()
+            .filter(|m| self.outcome(m) == CollisionOutcome::Merge)
+            .map(|m| (m.a, m.b, m.offset))
+            .collect();
+        for (a, b, offset) in merging{
+            //one of them may already be gone into a third body this step
+            let (body_a, body_b) = match (self.body(a), self.body(b)){
+                (Some(body_a), Some(body_b)) => (body_a, body_b),
+                _ => continue,
+            };
+            let (survivor, absorbed, shift) = if body_a.mass() >= body_b.mass() { (a, b, offset) } else { (b, a, -offset) };
+            //across a periodic edge the absorbed body is merged in where the survivor saw it
+            let mut absorbed_body = self.body(absorbed).unwrap().clone();
+            absorbed_body.position += shift;
+            let mut merged = merge::merge_bodies(self.body(survivor).unwrap(), &absorbed_body);
+            if let Some(periodic) = &self.periodic{
+                merged.position = periodic.wrap(merged.position);
+            }
+            *self.body_mut(survivor).unwrap() = merged;
+
+            //the pair never really touched as far as begin/end events go
+            self.touching.retain(|(x, y)| !(*x == a && *y == b));
+            begins.retain(|(x, y)| *x != absorbed && *y != absorbed);
+            self.remove_body(absorbed);
+            self.merge_events.push(MergeEvent{
+                survivor,
+                absorbed,
+            });
+        }
+        //contacts of the survivor are stale now, they get rebuilt next step
+        let events = &self.merge_events;
+        self.manifolds.retain(|m| !events.iter().any(|e| m.a == e.survivor || m.b == e.survivor));
+    }
+
+    //breaks whatever got hit harder than its threshold this step, the pieces keep the velocities the solver gave the body
+    fn break_bodies(&mut self){
+        let mut hardest: HashMap<BodyHandle, f32> = HashMap::new();
+        for manifold in self.manifolds.iter().filter(|m| m.enabled){
+            let impulse = manifold.total_normal_impulse();
+            for handle in [manifold.a, manifold.b]{
+                let entry = hardest.entry(handle).or_insert(0.0);
+                *entry = entry.max(impulse);
+            }
+        }
+        let mut breaking: Vec<BodyHandle> = hardest.into_iter()
+            .filter(|(handle, impulse)| {
+                let body = self.bodies[handle.0].as_ref().unwrap();
+                body.is_dynamic() && body.breakable.is_some_and(|breakable| *impulse > breakable.threshold)
+            })
+            .map(|(handle, _)| handle)
+            .collect();
+        breaking.sort();
+
+        for handle in breaking{
+            let body = self.bodies[handle.0].as_ref().unwrap();
+            let pieces = body.breakable.unwrap().fragments;
+            let fragments = fracture::fracture_body(body, pieces, &mut self.fracture_rng);
+            if fragments.is_empty(){
+                continue;
+            }
+            //the fragments go in before the body comes out, otherwise the first one would take over
+            //its slot and the event would name it as both the broken body and a fragment
+            let fragments = fragments.into_iter().map(|fragment| self.add_body(fragment)).collect();
+            self.remove_body(handle);
+            self.fracture_events.push(FractureEvent{
+                body: handle,
+                fragments,
+            });
+        }
+    }
+
+    fn pre_solve(&mut self){
+        let mut listener = match self.listener.take(){
+            Some(listener) => listener,
+            None => return,
+        };
+        for manifold in self.manifolds.iter_mut(){
+            let a = self.bodies[manifold.a.0].as_ref().unwrap();
+            let b = self.bodies[manifold.b.0].as_ref().unwrap();
+            manifold.enabled = listener.pre_solve(manifold, a, b);
+            if !manifold.enabled{
+                for point in manifold.points.iter_mut(){
+                    point.normal_impulse = 0.0;
+                    point.tangent_impulse = 0.0;
+                }
+            }
+        }
+        self.listener = Some(listener);
+    }
+
+    //begin events wait until here so they can carry the impact impulse
+    fn post_solve(&mut self, begins: &HashSet<(BodyHandle, BodyHandle)>, closing_speeds: &[f32]){
+        let mut listener = self.listener.take();
+        for (manifold, speed) in self.manifolds.iter().zip(closing_speeds){
+            let event = events::contact_event(manifold, *speed);
+            if begins.contains(&(manifold.a, manifold.b)){
+                self.collision_events.push(CollisionEvent::Begin(event));
+                if let Some(listener) = listener.as_mut(){
+                    listener.begin_contact(&event);
+                }
+            }
+            if manifold.enabled{
+                if let Some(listener) = listener.as_mut(){
+                    listener.post_solve(&event);
+                }
+            }
+        }
+        if let Some(listener) = listener.as_mut(){
+            for event in self.collision_events.iter(){
+                if let CollisionEvent::End{ a, b } = event{
+                    listener.end_contact(*a, *b);
+                }
+            }
+        }
+        self.listener = listener;
+    }
+
+    //returns the pairs that started touching this step
+    fn find_contacts(&mut self) -> HashSet<(BodyHandle, BodyHandle)>{
+        let previous: HashMap<(BodyHandle, BodyHandle), Manifold> = self.manifolds.drain(..).map(|m| ((m.a, m.b), m)).collect();
+        self.rebuild_broadphase();
+        let mut sensor_overlaps = HashSet::new();
+        let mut touching = HashSet::new();
+        for (i, j) in self.broadphase.pairs(){
+            let a = self.bodies[i].as_ref().unwrap();
+            let b = self.bodies[j].as_ref().unwrap();
+            if !self.filter_pair(BodyHandle(i), a, BodyHandle(j), b){
+                continue;
+            }
+            //moves b next to a when they meet across a periodic edge
+            let offset = self.separation(a.position, b.position) - (b.position - a.position);
+            if a.is_sensor || b.is_sensor{
+                if a.is_sensor && b.is_sensor{
+                    continue;
+                }
+                let overlapping = collision::collide_shapes(&a.shape, a.position, a.angle, &b.shape, b.position + offset, b.angle).is_some();
+                if overlapping{
+                    sensor_overlaps.insert(if a.is_sensor { (BodyHandle(i), BodyHandle(j)) } else { (BodyHandle(j), BodyHandle(i)) });
+                }
+                continue;
+            }
+            //at least one of the two has to be moving
+            let active = |body: &Body| (body.is_dynamic() && body.is_awake()) || (body.is_kinematic() && body.is_moving());
+            if !active(a) && !active(b){
+                //sleeping bodies keep touching whatever they fell asleep on
+                if self.touching.contains(&(BodyHandle(i), BodyHandle(j))){
+                    touching.insert((BodyHandle(i), BodyHandle(j)));
+                }
+                continue;
+            }
+            if let Some(mut manifold) = collision::collide(BodyHandle(i), a, BodyHandle(j), b, offset, &self.combine_rules){
+                if let Some(old) = previous.get(&(manifold.a, manifold.b)){
+                    carry_impulses(&mut manifold, old);
+                }
+                touching.insert((manifold.a, manifold.b));
+                self.manifolds.push(manifold);
+            }
+        }
+        self.sensors.update(sensor_overlaps);
+
+        let begins: HashSet<(BodyHandle, BodyHandle)> = touching.difference(&self.touching).copied().collect();
+        let mut ended: Vec<(BodyHandle, BodyHandle)> = self.touching.difference(&touching).copied().collect();
+        ended.sort();
+        for (a, b) in ended{
+            self.collision_events.push(CollisionEvent::End{ a, b });
+        }
+        self.touching = touching;
+        begins
+    }
+}
+
+fn carry_impulses(manifold: &mut Manifold, old: &Manifold){
+    for point in manifold.points.iter_mut(){
+        let closest = old.points.iter()
+            .map(|p| ((p.point - point.point).length_squared(), p))
+            .min_by(|x, y| x.0.total_cmp(&y.0));
+        if let Some((dist_sq, old_point)) = closest{
+            if dist_sq < WARM_START_DISTANCE * WARM_START_DISTANCE{
+                point.normal_impulse = old_point.normal_impulse;
+                point.tangent_impulse = old_point.tangent_impulse;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::filter::CollisionFilter;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn ground(world: &mut World) -> BodyHandle{
+        world.add_body(Body::new_static(Shape::rect(40.0, 1.0), Vec2::new(0.0, -0.5)))
+    }
+
+    fn run(world: &mut World, steps: usize){
+        for _ in 0..steps{
+            world.step(DT);
+        }
+    }
+
+    #[test]
+    fn resting_stack_sleeps_and_impact_wakes_its_island(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        ground(&mut world);
+        let stack: Vec<BodyHandle> = (0..5).map(|i| {
+            world.add_body(Body::new(Shape::rect(1.0, 1.0), Vec2::new(0.0, 0.5 + i as f32 * 1.01)))
+        }).collect();
+        let bystander = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(6.0, 0.5)));
+        run(&mut world, 300);
+        assert_eq!(world.sleeping_body_count(), 6);
+        assert_eq!(world.awake_island_count(), 0);
+
+        //a ball dropped on the top box wakes every box in the stack, not just the one it hits
+        world.add_body(Body::new(Shape::circle(0.3), Vec2::new(0.0, 6.0)));
+        let mut woken = false;
+        for _ in 0..60{
+            world.step(DT);
+            if stack.iter().all(|h| world.body(*h).unwrap().is_awake()){
+                woken = true;
+                break;
+            }
+        }
+        assert!(woken);
+        assert!(!world.body(bystander).unwrap().is_awake());
+    }
+
+    #[test]
+    fn filtered_pairs_pass_through(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        ground(&mut world);
+        let mut ghost = Body::new(Shape::circle(0.5), Vec2::new(0.0, 2.0));
+        ghost.filter = CollisionFilter::new(2, !1);
+        let ghost = world.add_body(ghost);
+        let ball = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(3.0, 2.0)));
+        let blocked = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(6.0, 2.0)));
+        world.set_pair_filter(Box::new(move |a, _, b, _| a != blocked && b != blocked));
+        run(&mut world, 60);
+        assert!(world.body(ghost).unwrap().position.y < -1.0);
+        assert!(world.body(ball).unwrap().position.y > 0.4);
+        assert!(world.body(blocked).unwrap().position.y < -1.0);
+        assert!(!world.should_collide(ghost, ball) && world.should_collide(ball, BodyHandle(0)));
+    }
+
+    //counts what the listener was told, shared with the test through a cell
+    #[derive(Default)]
+    struct Counts{
+        begins: usize,
+        ends: Vec<(BodyHandle, BodyHandle)>,
+        post_solves: usize,
+    }
+
+    struct CountingListener(std::rc::Rc<std::cell::RefCell<Counts>>);
+
+    impl ContactListener for CountingListener{
+        fn begin_contact(&mut self, _event: &events::ContactEvent){
+            self.0.borrow_mut().begins += 1;
+        }
+
+        fn end_contact(&mut self, a: BodyHandle, b: BodyHandle){
+            self.0.borrow_mut().ends.push((a, b));
+        }
+
+        fn post_solve(&mut self, _event: &events::ContactEvent){
+            self.0.borrow_mut().post_solves += 1;
+        }
+    }
+
+    #[test]
+    fn bouncing_ball_begins_and_ends_contacts(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        ground(&mut world);
+        let ball = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(0.0, 3.0)));
+        world.body_mut(ball).unwrap().restitution = 0.8;
+        let counts = std::rc::Rc::new(std::cell::RefCell::new(Counts::default()));
+        world.set_contact_listener(Box::new(CountingListener(counts.clone())));
+        let (mut begins, mut ends) = (0, 0);
+        for _ in 0..600{
+            world.step(DT);
+            for event in world.collision_events(){
+                match event{
+                    CollisionEvent::Begin(contact) => {
+                        assert!(contact.impulse > 0.0 && contact.normal.y.abs() > 0.99);
+                        begins += 1;
+                    },
+                    CollisionEvent::End{ .. } => ends += 1,
+                }
+            }
+        }
+        assert!(begins >= 2 && ends >= 1);
+        let counts = counts.borrow();
+        assert_eq!((counts.begins, counts.ends.len()), (begins, ends));
+        assert!(counts.post_solves > begins);
+    }
+
+    #[test]
+    fn removed_body_ends_its_contacts(){
+        let mut world = World::new(Vec2::new(0.0, -9.81));
+        let floor = ground(&mut world);
+        let ball = world.add_body(Body::new(Shape::circle(0.5), Vec2::new(0.0, 0.5)));
+        let counts = std::rc::Rc::new(std::cell::RefCell::new(Counts::default()));
+        world.set_contact_listener(Box::new(CountingListener(counts.clone())));
+        run(&mut world, 10);
+        assert!(world.is_touching(floor, ball));
+        world.remove_body(ball);
+        assert!(counts.borrow().ends.is_empty());
+        world.step(DT);
+        assert_eq!(counts.borrow().ends, vec![(floor, ball)]);
+        assert_eq!(world.collision_events(), &[CollisionEvent::End{ a: floor, b: ball }]);
+        //and only once
+        world.step(DT);
+        assert_eq!(counts.borrow().ends.len(), 1);
+    }
+
+    #[test]
+    fn queries_forget_removed_bodies(){
+        let mut world = World::new(Vec2::zero());
+        let old = world.add_body(Body::new(Shape::circle(1.0), Vec2::new(5.0, 0.0)));
+        world.step(DT);
+        world.remove_body(old);
+        let everything = Aabb::new(Vec2::new(-20.0, -20.0), Vec2::new(20.0, 20.0));
+        assert!(world.query_aabb(&everything, |_, _| true).is_empty());
+        //the new body gets the old slot but must only be found where it is
+        let new = world.add_body(Body::new(Shape::circle(1.0), Vec2::new(10.0, 0.0)));
+        assert_eq!(new, old);
+        assert!(world.query_point(Vec2::new(5.0, 0.0), |_, _| true).is_empty());
+        assert_eq!(world.query_aabb(&everything, |_, _| true), vec![new]);
+        let hits = world.raycast_all(Vec2::zero(), Vec2::new(20.0, 0.0), |_, _| true);
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].fraction - 0.45).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rays_and_shape_casts_hit_the_nearest_body(){
+        let mut world = World::new(Vec2::zero());
+        let circle = world.add_body(Body::new(Shape::circle(1.0), Vec2::new(5.0, 0.0)));
+        let square = world.add_body(Body::new(Shape::rect(2.0, 2.0), Vec2::new(10.0, 0.0)));
+        let (from, to) = (Vec2::zero(), Vec2::new(20.0, 0.0));
+        let hit = world.raycast(from, to, |_, _| true).unwrap();
+        assert_eq!(hit.body, circle);
+        assert!((hit.fraction - 0.2).abs() < 1e-4 && (hit.normal.x + 1.0).abs() < 1e-4);
+        let hits = world.raycast_all(from, to, |_, _| true);
+        assert_eq!(hits.iter().map(|h| h.body).collect::<Vec<_>>(), vec![circle, square]);
+        assert!((hits[1].fraction - 0.45).abs() < 1e-4);
+        assert_eq!(world.raycast(from, to, |h, _| h != circle).unwrap().body, square);
+
+        let hit = world.shape_cast(&Shape::circle(0.5), 0.0, from, to, |_, _| true).unwrap();
+        assert_eq!(hit.body, circle);
+        assert!((hit.fraction - 3.5 / 20.0).abs() < 1e-4);
+        //a box skipping the circle reaches the square
+        let hit = world.shape_cast(&Shape::rect(1.0, 1.0), 0.0, Vec2::new(0.0, 1.2), Vec2::new(20.0, 1.2), |h, _| h != circle).unwrap();
+        assert_eq!(hit.body, square);
+        assert!((hit.fraction - 8.5 / 20.0).abs() < 1e-4);
+        assert!(world.shape_cast(&Shape::rect(1.0, 1.0), 0.0, Vec2::new(0.0, 3.0), Vec2::new(20.0, 3.0), |_, _| true).is_none());
+    }
+
+    #[test]
+    fn overlap_queries_check_the_real_shapes(){
+        let mut world = World::new(Vec2::zero());
+        let circle = world.add_body(Body::new(Shape::circle(1.0), Vec2::new(5.0, 0.0)));
+        let square = world.add_body(Body::new(Shape::rect(2.0, 2.0), Vec2::new(10.0, 0.0)));
+        assert_eq!(world.query_point(Vec2::new(5.5, 0.5), |_, _| true), vec![circle]);
+        //inside the circle's box but outside the circle
+        assert!(world.query_point(Vec2::new(5.9, 0.9), |_, _| true).is_empty());
+        let region = Aabb::new(Vec2::new(0.0, -5.0), Vec2::new(20.0, 5.0));
+        assert_eq!(world.query_aabb(&region, |_, _| true), vec![circle, square]);
+        assert_eq!(world.query_aabb(&region, |_, body| body.shape.area() > 3.5), vec![square]);
+        assert!(world.query_shape(&Shape::circle(1.1), Vec2::new(7.5, 0.0), 0.0, |_, _| true).is_empty());
+        assert_eq!(world.query_shape(&Shape::circle(1.6), Vec2::new(7.5, 0.0), 0.0, |_, _| true), vec![circle, square]);
+    }
+}